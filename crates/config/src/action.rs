@@ -2,19 +2,35 @@ use std::collections::{BTreeMap, HashMap};
 use std::ops::{Deref, DerefMut};
 
 use merge2::Merge;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
-#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
 pub enum NotificationAction {
     #[default]
     OpenNotification,
     Close,
+    /// Show the next notification of the same application
+    NextNotification,
+    /// Show the previous notification of the same application
+    PreviousNotification,
+    /// Invoke the action of the notification with the key, like `{ InvokeAction = "reply" }`
+    InvokeAction(String),
+    /// Copy the body of the notification to the clipboard
+    CopyBody,
+    /// Hide the notification and show it again after the seconds, like `{ Snooze = 300.0 }`
+    Snooze(f32),
+    /// Close the notification and the grouped, stacked and snoozed ones
+    DismissAll,
+    /// Browse the last notifications with `NextNotification` and `PreviousNotification`
+    ShowHistory,
 }
 
-#[derive(
-    Debug, Hash, Default, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize,
-)]
+/// Input bound to an action, any name other than the pointer and touch ones is a key
+///
+/// Keys are named like `Escape`, `Enter`, `ArrowUp` or by their character like `c`
+#[derive(Debug, Hash, Default, Clone, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 #[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
 pub enum InputAction {
     #[default]
@@ -23,8 +39,56 @@ pub enum InputAction {
     MiddleClick,
     ScrollUp,
     ScrollDown,
+    TouchTap,
+    TouchLongPress,
     TouchSwipeUp,
     TouchSwipeDown,
+    TouchSwipeLeft,
+    TouchSwipeRight,
+    TouchTwoFingerSwipeUp,
+    TouchTwoFingerSwipeDown,
+    TouchTwoFingerSwipeLeft,
+    TouchTwoFingerSwipeRight,
+    Key(String),
+}
+
+impl InputAction {
+    /// Every input but the keys
+    const NAMED: [Self; 15] = [
+        Self::LeftClick,
+        Self::RightClick,
+        Self::MiddleClick,
+        Self::ScrollUp,
+        Self::ScrollDown,
+        Self::TouchTap,
+        Self::TouchLongPress,
+        Self::TouchSwipeUp,
+        Self::TouchSwipeDown,
+        Self::TouchSwipeLeft,
+        Self::TouchSwipeRight,
+        Self::TouchTwoFingerSwipeUp,
+        Self::TouchTwoFingerSwipeDown,
+        Self::TouchTwoFingerSwipeLeft,
+        Self::TouchTwoFingerSwipeRight,
+    ];
+}
+
+impl From<String> for InputAction {
+    fn from(name: String) -> Self {
+        Self::NAMED
+            .into_iter()
+            .find(|action| format!("{action:?}") == name)
+            .unwrap_or(Self::Key(name))
+    }
+}
+
+impl From<InputAction> for String {
+    fn from(action: InputAction) -> Self {
+        match action {
+            InputAction::Key(name) => name,
+            action => format!("{action:?}"),
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
@@ -37,10 +101,47 @@ pub enum InputModifier {
     Super,
 }
 
+/// A value or a list of them, the configs before the lists had a single value
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> From<OneOrMany<T>> for Vec<T> {
+    fn from(value: OneOrMany<T>) -> Self {
+        match value {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+fn one_or_many<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    deserializer: D,
+) -> Result<Vec<T>, D::Error> {
+    OneOrMany::deserialize(deserializer).map(Vec::from)
+}
+
+fn bindings<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<InputAction, Vec<InputEvent>>, D::Error> {
+    let bindings = BTreeMap::<InputAction, OneOrMany<InputEvent>>::deserialize(deserializer)?;
+    Ok(bindings.into_iter().map(|(k, v)| (k, v.into())).collect())
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
 pub struct InputEvent {
-    pub modifier: Option<InputModifier>,
+    /// Modifiers held down together, the old `modifier = "Ctrl"` is read as one of them
+    #[serde(
+        default,
+        alias = "modifier",
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub modifiers: Vec<InputModifier>,
     pub action: NotificationAction,
 }
 
@@ -48,21 +149,30 @@ impl InputEvent {
     pub fn new(action: NotificationAction) -> Self {
         Self {
             action,
-            modifier: None,
+            modifiers: Vec::new(),
         }
     }
 
     pub fn with_modifiers(mut self, modifier: InputModifier) -> Self {
-        self.modifier = Some(modifier);
+        self.modifiers.push(modifier);
         self
     }
+
+    /// Whether the modifiers held down are the ones of the binding, no more and no less
+    pub fn matches(&self, pressed: &[InputModifier]) -> bool {
+        self.modifiers.iter().all(|m| pressed.contains(m))
+            && pressed.iter().all(|m| self.modifiers.contains(m))
+    }
 }
 
+/// Bindings of each input, told apart by their modifiers
+///
+/// An input takes a table or, with several bindings, an array of tables
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Merge)]
 #[merge(strategy = merge2::any::overwrite)]
 #[serde(transparent)]
 #[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
-pub struct Action(BTreeMap<InputAction, InputEvent>);
+pub struct Action(#[serde(deserialize_with = "bindings")] BTreeMap<InputAction, Vec<InputEvent>>);
 
 impl Action {
     pub fn new() -> Self {
@@ -70,7 +180,16 @@ impl Action {
     }
 
     pub fn add(&mut self, action: InputAction, event: InputEvent) {
-        self.0.entry(action).or_insert(event);
+        self.0.entry(action).or_default().push(event);
+    }
+
+    /// Binding of the input with the modifiers held down, or else the one without modifiers
+    pub fn find(&self, action: &InputAction, pressed: &[InputModifier]) -> Option<&InputEvent> {
+        let events = self.0.get(action)?;
+        events
+            .iter()
+            .find(|e| e.matches(pressed))
+            .or_else(|| events.iter().find(|e| e.modifiers.is_empty()))
     }
 }
 
@@ -81,6 +200,10 @@ impl Default for Action {
             InputAction::LeftClick,
             InputEvent::new(NotificationAction::OpenNotification),
         );
+        action.add(
+            InputAction::LeftClick,
+            InputEvent::new(NotificationAction::DismissAll).with_modifiers(InputModifier::Shift),
+        );
         action.add(
             InputAction::RightClick,
             InputEvent::new(NotificationAction::Close),
@@ -89,18 +212,42 @@ impl Default for Action {
             InputAction::ScrollUp,
             InputEvent::new(NotificationAction::Close),
         );
+        action.add(
+            InputAction::ScrollDown,
+            InputEvent::new(NotificationAction::NextNotification),
+        );
+        action.add(
+            InputAction::TouchTap,
+            InputEvent::new(NotificationAction::OpenNotification),
+        );
+        action.add(
+            InputAction::TouchSwipeLeft,
+            InputEvent::new(NotificationAction::Close),
+        );
+        action.add(
+            InputAction::TouchSwipeRight,
+            InputEvent::new(NotificationAction::Close),
+        );
+        action.add(
+            InputAction::Key("Escape".to_owned()),
+            InputEvent::new(NotificationAction::Close),
+        );
+        action.add(
+            InputAction::Key("Enter".to_owned()),
+            InputEvent::new(NotificationAction::OpenNotification),
+        );
         action
     }
 }
 
 impl From<HashMap<InputAction, InputEvent>> for Action {
     fn from(map: HashMap<InputAction, InputEvent>) -> Self {
-        Self(BTreeMap::from_iter(map.into_iter()))
+        Self(map.into_iter().map(|(k, v)| (k, vec![v])).collect())
     }
 }
 
 impl Deref for Action {
-    type Target = BTreeMap<InputAction, InputEvent>;
+    type Target = BTreeMap<InputAction, Vec<InputEvent>>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -112,3 +259,56 @@ impl DerefMut for Action {
         &mut self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bindings_migration() {
+        let old = r#"
+            [LeftClick]
+            action = "OpenNotification"
+
+            [RightClick]
+            modifier = "Ctrl"
+            action = "Close"
+        "#;
+        let action: Action = toml::from_str(old).unwrap();
+        let close = InputEvent::new(NotificationAction::Close).with_modifiers(InputModifier::Ctrl);
+        assert_eq!(action[&InputAction::RightClick], [close]);
+
+        let new = r#"
+            [[LeftClick]]
+            action = "OpenNotification"
+
+            [[LeftClick]]
+            modifiers = ["Ctrl", "Shift"]
+            action = "DismissAll"
+        "#;
+        let action: Action = toml::from_str(new).unwrap();
+        let find = |pressed: &[InputModifier]| {
+            action
+                .find(&InputAction::LeftClick, pressed)
+                .map(|e| e.action.clone())
+        };
+        assert_eq!(find(&[]), Some(NotificationAction::OpenNotification));
+        assert_eq!(
+            find(&[InputModifier::Shift, InputModifier::Ctrl]),
+            Some(NotificationAction::DismissAll)
+        );
+        // Without a binding for the modifiers held down the plain one is used
+        assert_eq!(
+            find(&[InputModifier::Shift]),
+            Some(NotificationAction::OpenNotification)
+        );
+        assert_eq!(action.find(&InputAction::RightClick, &[]), None);
+
+        // The defaults are written back as arrays of tables
+        let default = toml::to_string(&Action::default()).unwrap();
+        assert_eq!(
+            toml::from_str::<Action>(&default).unwrap(),
+            Action::default()
+        );
+    }
+}
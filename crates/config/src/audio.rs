@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct AudioConfig {
+    pub enabled: bool,
+    /// The default sink, speakers or headphones
+    pub output: AudioDeviceConfig,
+    /// The default source, the microphone
+    pub input: AudioDeviceConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct AudioDeviceConfig {
+    pub icon: String,
+    /// Icon used while the device is muted
+    pub muted_icon: String,
+    /// The animation duration to show the widget (in seconds)
+    pub show_duration: Option<f32>,
+    /// Background Color of widget, support: '#RRGGBBAA', '#RGBA' and '#RGB'
+    pub background: Option<String>,
+    /// Foreground Color of widget, support: '#RRGGBBAA', '#RGBA' and '#RGB'
+    pub foreground: Option<String>,
+    /// Background Color of widget while muted, support: '#RRGGBBAA', '#RGBA' and '#RGB'
+    pub muted_background: Option<String>,
+    /// Foreground Color of widget while muted, support: '#RRGGBBAA', '#RGBA' and '#RGB'
+    pub muted_foreground: Option<String>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            output: AudioDeviceConfig {
+                icon: "󰕾".to_owned(),
+                muted_icon: "󰝟".to_owned(),
+                show_duration: Some(2.0),
+                background: None,
+                foreground: None,
+                muted_background: Some("#ff6961".to_owned()),
+                muted_foreground: Some("#fff".to_owned()),
+            },
+            input: AudioDeviceConfig {
+                icon: "󰍬".to_owned(),
+                muted_icon: "󰍭".to_owned(),
+                show_duration: Some(2.0),
+                background: None,
+                foreground: None,
+                muted_background: Some("#ff6961".to_owned()),
+                muted_foreground: Some("#fff".to_owned()),
+            },
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+
+use clap::{Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct BrightnessConfig {
+    pub enabled: bool,
+    /// Icon used for devices without their own entry
+    pub icon: String,
+    /// Per device overrides, by sysfs name like 'intel_backlight' or 'tpacpi::kbd_backlight'
+    pub devices: BTreeMap<String, BrightnessDevice>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct BrightnessDevice {
+    pub icon: String,
+    /// The animation duration to show the widget (in seconds)
+    pub show_duration: Option<f32>,
+    /// Background Color of widget, support: '#RRGGBBAA', '#RGBA' and '#RGB'
+    pub background: Option<String>,
+    /// Foreground Color of widget, support: '#RRGGBBAA', '#RGBA' and '#RGB'
+    pub foreground: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+pub enum BrightnessSubsystem {
+    #[default]
+    Backlight,
+    Leds,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BrightnessCommand {
+    /// Set the brightness to a percentage
+    Set {
+        /// Value from 0 to 100
+        value: u8,
+    },
+    /// Increase the brightness by a percentage
+    Inc {
+        /// Value from 0 to 100
+        #[clap(default_value = "5")]
+        value: u8,
+    },
+    /// Decrease the brightness by a percentage
+    Dec {
+        /// Value from 0 to 100
+        #[clap(default_value = "5")]
+        value: u8,
+    },
+}
+
+impl BrightnessSubsystem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BrightnessSubsystem::Backlight => "backlight",
+            BrightnessSubsystem::Leds => "leds",
+        }
+    }
+}
+
+impl Default for BrightnessConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            icon: "󰃠".to_owned(),
+            devices: BTreeMap::from_iter([(
+                "tpacpi::kbd_backlight".to_owned(),
+                BrightnessDevice {
+                    icon: "󰌌".to_owned(),
+                    show_duration: None,
+                    background: None,
+                    foreground: None,
+                },
+            )]),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct HotplugConfig {
+    pub enabled: bool,
+    /// Kernel subsystems to show, support: 'usb', 'input', 'sound' and 'drm'
+    pub subsystems: Vec<String>,
+    /// Only show the devices matching any of these, by 'vendor:product' id or part of the name [default: all]
+    pub allow: Vec<String>,
+    /// Never show the devices matching any of these, by 'vendor:product' id or part of the name
+    pub deny: Vec<String>,
+    /// The animation duration to show the widget (in seconds)
+    pub show_duration: Option<f32>,
+    pub added_icon: String,
+    pub removed_icon: String,
+    pub display_icon: String,
+}
+
+impl Default for HotplugConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            subsystems: ["usb", "input", "sound", "drm"].map(String::from).to_vec(),
+            allow: Vec::new(),
+            deny: Vec::new(),
+            show_duration: Some(2.0),
+            added_icon: "󱊟".to_owned(),
+            removed_icon: "󱊠".to_owned(),
+            display_icon: "󰍹".to_owned(),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{BatteryLevel, BatteryLevelAlerts};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct HwmonConfig {
+    pub enabled: bool,
+    pub refresh_time: f32,
+    /// Degrees a sensor must cool below a reached threshold before it can alert again
+    pub hysteresis: f32,
+    /// Sensors to watch by label, chip or both like 'k10temp/Tctl' [default: all]
+    pub sensors: Vec<String>,
+    /// Alerts by temperature threshold (in degrees Celsius)
+    pub level: Option<BatteryLevelAlerts>,
+}
+
+impl Default for HwmonConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_time: 5.0,
+            hysteresis: 5.0,
+            sensors: Vec::new(),
+            level: Some(BatteryLevelAlerts(BTreeMap::from_iter([
+                (
+                    80,
+                    BatteryLevel {
+                        icon: "󰔏".to_owned(),
+                        show_duration: Some(5.0),
+                        background: Some("#000".to_owned()),
+                        foreground: Some("#fff".to_owned()),
+                    },
+                ),
+                // Throttling
+                (
+                    95,
+                    BatteryLevel {
+                        icon: "󰸁".to_owned(),
+                        show_duration: Some(10.0),
+                        background: Some("#ff6961".to_owned()),
+                        foreground: Some("#fff".to_owned()),
+                    },
+                ),
+            ]))),
+        }
+    }
+}
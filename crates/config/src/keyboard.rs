@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct KeyboardConfig {
+    pub enabled: bool,
+    /// How often the lock leds are read (in seconds)
+    pub refresh_time: f32,
+    /// The animation duration to show the widget (in seconds)
+    pub show_duration: Option<f32>,
+    pub caps_lock: KeyboardLockConfig,
+    pub num_lock: KeyboardLockConfig,
+    pub scroll_lock: KeyboardLockConfig,
+    /// Icon shown when the active layout changes
+    pub layout_icon: String,
+    /// Short label for each layout name, like 'English (US)' = 'US'
+    pub layouts: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct KeyboardLockConfig {
+    pub enabled: bool,
+    /// Icon used while the lock is active
+    pub on_icon: String,
+    /// Icon used while the lock is inactive
+    pub off_icon: String,
+}
+
+impl Default for KeyboardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_time: 0.2,
+            show_duration: Some(1.0),
+            caps_lock: KeyboardLockConfig {
+                enabled: true,
+                on_icon: "󰪛".to_owned(),
+                off_icon: "󰬶".to_owned(),
+            },
+            num_lock: KeyboardLockConfig {
+                enabled: true,
+                on_icon: "󰎠".to_owned(),
+                off_icon: "󰎣".to_owned(),
+            },
+            scroll_lock: KeyboardLockConfig {
+                enabled: false,
+                on_icon: "󰌌".to_owned(),
+                off_icon: "󰌐".to_owned(),
+            },
+            layout_icon: "󰌌".to_owned(),
+            layouts: BTreeMap::new(),
+        }
+    }
+}
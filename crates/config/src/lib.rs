@@ -1,5 +1,15 @@
 mod action;
+mod audio;
 mod battery;
+mod brightness;
+mod hotplug;
+mod hwmon;
+mod keyboard;
+mod mpris;
+mod network;
+mod power;
+mod privacy;
+mod storage;
 mod types;
 mod urgency;
 
@@ -8,9 +18,19 @@ use std::path::{Path, PathBuf};
 use merge2::Merge;
 
 pub use action::*;
+pub use audio::*;
 pub use battery::*;
+pub use brightness::*;
 pub use clap::Parser;
 pub use directories::ProjectDirs;
+pub use hotplug::*;
+pub use hwmon::*;
+pub use keyboard::*;
+pub use mpris::*;
+pub use network::*;
+pub use power::*;
+pub use privacy::*;
+pub use storage::*;
 pub use types::*;
 pub use urgency::*;
 
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct MprisConfig {
+    pub enabled: bool,
+    /// Icon used when the track has no album art
+    pub icon: String,
+    /// The animation duration to show the widget (in seconds)
+    pub show_duration: Option<f32>,
+    /// Background Color of widget, support: '#RRGGBBAA', '#RGBA' and '#RGB'
+    pub background: Option<String>,
+    /// Foreground Color of widget, support: '#RRGGBBAA', '#RGBA' and '#RGB'
+    pub foreground: Option<String>,
+}
+
+impl Default for MprisConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            icon: "󰝚".to_owned(),
+            show_duration: Some(4.0),
+            background: None,
+            foreground: None,
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct NetworkConfig {
+    pub enabled: bool,
+    pub wifi_connected: NetworkEventConfig,
+    pub wifi_disconnected: NetworkEventConfig,
+    pub ethernet_connected: NetworkEventConfig,
+    pub ethernet_disconnected: NetworkEventConfig,
+    pub vpn_connected: NetworkEventConfig,
+    pub vpn_disconnected: NetworkEventConfig,
+    /// When there is no connection left
+    pub offline: NetworkEventConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct NetworkEventConfig {
+    pub enabled: bool,
+    pub icon: String,
+    /// The animation duration to show the widget (in seconds)
+    pub show_duration: Option<f32>,
+    /// Background Color of widget, support: '#RRGGBBAA', '#RGBA' and '#RGB'
+    pub background: Option<String>,
+    /// Foreground Color of widget, support: '#RRGGBBAA', '#RGBA' and '#RGB'
+    pub foreground: Option<String>,
+}
+
+impl NetworkEventConfig {
+    fn new(icon: &str) -> Self {
+        Self {
+            enabled: true,
+            icon: icon.to_owned(),
+            show_duration: Some(3.0),
+            background: None,
+            foreground: None,
+        }
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            wifi_connected: NetworkEventConfig::new("󰖩"),
+            wifi_disconnected: NetworkEventConfig::new("󰖪"),
+            ethernet_connected: NetworkEventConfig::new("󰈀"),
+            ethernet_disconnected: NetworkEventConfig::new("󰈂"),
+            vpn_connected: NetworkEventConfig::new("󰦝"),
+            vpn_disconnected: NetworkEventConfig::new("󰦞"),
+            offline: NetworkEventConfig {
+                background: Some("#ff6961".to_owned()),
+                foreground: Some("#fff".to_owned()),
+                ..NetworkEventConfig::new("󰤮")
+            },
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct PowerConfig {
+    pub enabled: bool,
+    /// The animation duration to show the widget (in seconds)
+    pub show_duration: Option<f32>,
+    pub power_saver_icon: String,
+    pub balanced_icon: String,
+    pub performance_icon: String,
+    /// Show the battery level after resuming from suspend
+    pub show_on_resume: bool,
+    pub resume_icon: String,
+    /// Show when the lid is opened or closed
+    pub show_lid: bool,
+    pub lid_icon: String,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            show_duration: Some(2.0),
+            power_saver_icon: "󰌪".to_owned(),
+            balanced_icon: "󰗑".to_owned(),
+            performance_icon: "󱐋".to_owned(),
+            show_on_resume: true,
+            resume_icon: "󰁹".to_owned(),
+            show_lid: false,
+            lid_icon: "󰌢".to_owned(),
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct PrivacyConfig {
+    pub enabled: bool,
+    pub microphone_icon: String,
+    pub camera_icon: String,
+    /// Background Color of widget, support: '#RRGGBBAA', '#RGBA' and '#RGB'
+    pub background: Option<String>,
+    /// Foreground Color of widget, support: '#RRGGBBAA', '#RGBA' and '#RGB'
+    pub foreground: Option<String>,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            microphone_icon: "󰍬".to_owned(),
+            camera_icon: "󰄀".to_owned(),
+            background: Some("#ff9e3b".to_owned()),
+            foreground: Some("#000".to_owned()),
+        }
+    }
+}
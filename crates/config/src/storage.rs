@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{BatteryLevel, BatteryLevelAlerts};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub struct StorageConfig {
+    pub enabled: bool,
+    pub refresh_time: f32,
+    /// Mount points to check the free space of
+    pub mount_points: Vec<String>,
+    /// Alerts by free space threshold (in percent)
+    pub level: Option<BatteryLevelAlerts>,
+    /// Show when removable media is mounted or unmounted
+    pub show_mounts: bool,
+    /// The animation duration to show the mount widget (in seconds)
+    pub show_duration: Option<f32>,
+    pub mount_icon: String,
+    pub unmount_icon: String,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_time: 60.0,
+            mount_points: vec!["/".to_owned(), "/home".to_owned()],
+            level: Some(BatteryLevelAlerts(BTreeMap::from_iter([
+                (
+                    10,
+                    BatteryLevel {
+                        icon: "󰋊".to_owned(),
+                        show_duration: Some(5.0),
+                        background: Some("#000".to_owned()),
+                        foreground: Some("#fff".to_owned()),
+                    },
+                ),
+                // Almost full
+                (
+                    3,
+                    BatteryLevel {
+                        icon: "󱁌".to_owned(),
+                        show_duration: Some(10.0),
+                        background: Some("#ff6961".to_owned()),
+                        foreground: Some("#fff".to_owned()),
+                    },
+                ),
+            ]))),
+            show_mounts: true,
+            show_duration: Some(2.0),
+            mount_icon: "󰕓".to_owned(),
+            unmount_icon: "󰕔".to_owned(),
+        }
+    }
+}
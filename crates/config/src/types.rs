@@ -4,7 +4,11 @@ use clap::{Parser, Subcommand, ValueEnum};
 use merge2::Merge;
 use serde::{Deserialize, Serialize};
 
-use crate::{swap_option, Action, BatteryConfig, Urgency};
+use crate::{
+    swap_option, Action, AudioConfig, BatteryConfig, BrightnessCommand, BrightnessConfig,
+    BrightnessSubsystem, HotplugConfig, HwmonConfig, KeyboardConfig, MprisConfig, NetworkConfig,
+    PowerConfig, PrivacyConfig, StorageConfig, Urgency,
+};
 
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
 #[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
@@ -14,8 +18,87 @@ pub enum OsdPosition {
     Left,
     Right,
     Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
 }
 
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub enum OsdAlign {
+    /// Left or top edge
+    Start,
+    #[default]
+    Center,
+    /// Right or bottom edge
+    End,
+}
+
+impl OsdPosition {
+    /// Whether the widget grows from the bottom edge
+    pub fn is_bottom(self) -> bool {
+        matches!(self, Self::Bottom | Self::BottomLeft | Self::BottomRight)
+    }
+
+    /// Whether the widget is laid along a side, swapping its width and height
+    pub fn is_side(self) -> bool {
+        matches!(self, Self::Left | Self::Right)
+    }
+
+    /// Horizontal and vertical alignment in the screen
+    pub fn align(self) -> (OsdAlign, OsdAlign) {
+        match self {
+            Self::Top => (OsdAlign::Center, OsdAlign::Start),
+            Self::Left => (OsdAlign::Start, OsdAlign::Center),
+            Self::Right => (OsdAlign::End, OsdAlign::Center),
+            Self::Bottom => (OsdAlign::Center, OsdAlign::End),
+            Self::TopLeft => (OsdAlign::Start, OsdAlign::Start),
+            Self::TopRight => (OsdAlign::End, OsdAlign::Start),
+            Self::BottomLeft => (OsdAlign::Start, OsdAlign::End),
+            Self::BottomRight => (OsdAlign::End, OsdAlign::End),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub enum OsdLayout {
+    /// One notification at a time, the new ones replace it
+    #[default]
+    Single,
+    /// Several notifications at once, stacked away from the edge of the screen
+    Stack,
+}
+
+/// Layer-shell layer of the surface on Wayland, from the lowest to the highest
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub enum OsdLayer {
+    Background,
+    Bottom,
+    Top,
+    /// Over the fullscreen windows
+    #[default]
+    Overlay,
+}
+
+/// Keyboard focus of the surface on Wayland while the OSD is shown
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[cfg_attr(feature = "reflect", derive(mirror_mirror::Reflect))]
+pub enum OsdKeyboard {
+    /// Never takes the keyboard
+    #[default]
+    None,
+    /// Takes the keyboard when it is clicked
+    OnDemand,
+    /// Takes the keyboard as soon as it appears
+    Exclusive,
+}
+
+/// Value of `output` to show the OSD on the monitor with the focus
+pub const FOCUSED_OUTPUT: &str = "focused";
+
 fn serde_default_output() -> Option<String> {
     None
 }
@@ -33,7 +116,7 @@ pub struct Config {
     #[clap(flatten)]
     pub globals: Global,
 
-    /// Output Screen where notification has been showed
+    /// Output Screen where notification has been showed, 'focused' follows the focus
     #[clap(long, short)]
     #[serde(default = "serde_default_output")]
     pub output: Option<String>,
@@ -50,6 +133,56 @@ pub struct Config {
     #[merge(skip)]
     pub battery: BatteryConfig,
 
+    #[clap(skip)]
+    #[merge(skip)]
+    #[serde(default)]
+    pub brightness: BrightnessConfig,
+
+    #[clap(skip)]
+    #[merge(skip)]
+    #[serde(default)]
+    pub audio: AudioConfig,
+
+    #[clap(skip)]
+    #[merge(skip)]
+    #[serde(default)]
+    pub hwmon: HwmonConfig,
+
+    #[clap(skip)]
+    #[merge(skip)]
+    #[serde(default)]
+    pub hotplug: HotplugConfig,
+
+    #[clap(skip)]
+    #[merge(skip)]
+    #[serde(default)]
+    pub keyboard: KeyboardConfig,
+
+    #[clap(skip)]
+    #[merge(skip)]
+    #[serde(default)]
+    pub mpris: MprisConfig,
+
+    #[clap(skip)]
+    #[merge(skip)]
+    #[serde(default)]
+    pub network: NetworkConfig,
+
+    #[clap(skip)]
+    #[merge(skip)]
+    #[serde(default)]
+    pub power: PowerConfig,
+
+    #[clap(skip)]
+    #[merge(skip)]
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+
+    #[clap(skip)]
+    #[merge(skip)]
+    #[serde(default)]
+    pub storage: StorageConfig,
+
     #[clap(skip)]
     #[merge(skip)]
     pub urgency: UrgencyConfig,
@@ -80,6 +213,11 @@ pub struct Global {
     #[clap(long, short)]
     #[merge(strategy = swap_option)]
     pub foreground_color: Option<String>,
+    /// How long the widget stays after the pointer leaves it, the timeout is paused over it (in
+    /// seconds)
+    #[clap(long, default_value = "1.0")]
+    #[merge(strategy = swap_option)]
+    pub hover_grace: Option<f32>,
 }
 
 #[derive(Debug, Clone, PartialEq, Parser, Serialize, Deserialize, Merge)]
@@ -101,6 +239,57 @@ pub struct Window {
     #[clap(long, short = 'a')]
     #[merge(strategy = merge2::any::overwrite)]
     pub height: Option<u32>,
+    /// How the notifications are shown
+    #[clap(long, default_value = "single")]
+    #[merge(strategy = merge2::any::overwrite)]
+    #[serde(default)]
+    pub layout: OsdLayout,
+    /// Notifications visible at once with the stack layout [default: 3]
+    #[clap(long)]
+    #[merge(strategy = merge2::any::overwrite)]
+    pub stack_size: Option<u32>,
+    /// Horizontal alignment in the screen, by default the one of the position
+    #[clap(long)]
+    #[merge(strategy = merge2::any::overwrite)]
+    pub halign: Option<OsdAlign>,
+    /// Vertical alignment in the screen, by default the one of the position
+    #[clap(long)]
+    #[merge(strategy = merge2::any::overwrite)]
+    pub valign: Option<OsdAlign>,
+    /// Space between the widget and the top of the screen [default: 0]
+    #[clap(long)]
+    #[merge(strategy = merge2::any::overwrite)]
+    pub margin_top: Option<u32>,
+    /// Space between the widget and the bottom of the screen [default: 0]
+    #[clap(long)]
+    #[merge(strategy = merge2::any::overwrite)]
+    pub margin_bottom: Option<u32>,
+    /// Space between the widget and the left of the screen [default: 0]
+    #[clap(long)]
+    #[merge(strategy = merge2::any::overwrite)]
+    pub margin_left: Option<u32>,
+    /// Space between the widget and the right of the screen [default: 0]
+    #[clap(long)]
+    #[merge(strategy = merge2::any::overwrite)]
+    pub margin_right: Option<u32>,
+    /// Layer of the surface on Wayland
+    #[clap(long, default_value = "overlay")]
+    #[merge(strategy = merge2::any::overwrite)]
+    #[serde(default)]
+    pub layer: OsdLayer,
+    /// Keyboard focus on Wayland, with it Escape closes and Enter opens the notification
+    #[clap(long, default_value = "none")]
+    #[merge(strategy = merge2::any::overwrite)]
+    #[serde(default)]
+    pub keyboard: OsdKeyboard,
+}
+
+impl Window {
+    /// Horizontal and vertical alignment, the configured ones over the ones of the position
+    pub fn align(&self) -> (OsdAlign, OsdAlign) {
+        let (halign, valign) = self.position.align();
+        (self.halign.unwrap_or(halign), self.valign.unwrap_or(valign))
+    }
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -123,6 +312,9 @@ pub struct UrgencyItemConfig {
     /// Foreground Color of widget, support: '#RRGGBBAA', '#RGBA' and '#RGB'
     #[merge(strategy = swap_option)]
     pub foreground_color: Option<String>,
+    /// Keep the notifications without timeout on screen until they are closed
+    #[merge(strategy = swap_option)]
+    pub sticky: Option<bool>,
 }
 
 #[derive(Subcommand, Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -156,6 +348,38 @@ pub enum OsdType {
         #[clap(long, short)]
         urgency: Option<Urgency>,
     },
+    /// Slider updated in place while the same id is shown, for playback or download progress
+    Progress {
+        /// Id shared by the updates of the same progress
+        #[clap(long)]
+        id: u32,
+        /// Value for slider, from 0 to 100
+        #[clap(long, short)]
+        value: i32,
+        /// Image for notification, path or char
+        #[clap(long, short = 'm')]
+        image: Option<String>,
+        /// Urgency of notification
+        #[clap(long, short)]
+        urgency: Option<Urgency>,
+        /// Elapsed time shown next to the slider (in seconds)
+        #[clap(long, short)]
+        elapsed: Option<f32>,
+        /// Total time shown next to the elapsed time (in seconds)
+        #[clap(long, short = 'T')]
+        total: Option<f32>,
+    },
+    /// Change the brightness through logind, the daemon shows the change
+    Brightness {
+        /// Device name, like 'intel_backlight' [default: first device of the subsystem]
+        #[clap(long, short)]
+        device: Option<String>,
+        /// Subsystem of the device
+        #[clap(long, short, default_value = "backlight")]
+        subsystem: BrightnessSubsystem,
+        #[clap(subcommand)]
+        action: BrightnessCommand,
+    },
 }
 
 impl Default for Global {
@@ -165,6 +389,7 @@ impl Default for Global {
             show_duration: Some(5.0),
             background: Some("#000".to_owned()),
             foreground_color: Some("#fff".to_owned()),
+            hover_grace: Some(1.0),
         }
     }
 }
@@ -176,6 +401,16 @@ impl Default for Window {
             radius: Some(100),
             width: Some(600),
             height: Some(80),
+            layout: Default::default(),
+            stack_size: Some(3),
+            halign: None,
+            valign: None,
+            margin_top: Some(0),
+            margin_bottom: Some(0),
+            margin_left: Some(0),
+            margin_right: Some(0),
+            layer: Default::default(),
+            keyboard: Default::default(),
         }
     }
 }
@@ -186,6 +421,7 @@ impl Default for UrgencyItemConfig {
             show_duration: Some(5.0),
             background: None,
             foreground_color: None,
+            sticky: None,
         }
     }
 }
@@ -197,6 +433,7 @@ impl Default for Config {
             show_duration: Some(5.0),
             background: globals.background.clone(),
             foreground_color: globals.foreground_color.clone(),
+            sticky: None,
         };
 
         Self {
@@ -207,6 +444,16 @@ impl Default for Config {
             actions: Some(Action::default()),
             window: Some(Default::default()),
             battery: Default::default(),
+            brightness: Default::default(),
+            audio: Default::default(),
+            hwmon: Default::default(),
+            hotplug: Default::default(),
+            keyboard: Default::default(),
+            mpris: Default::default(),
+            network: Default::default(),
+            power: Default::default(),
+            privacy: Default::default(),
+            storage: Default::default(),
             urgency: UrgencyConfig {
                 low: urgency_default.clone(),
                 normal: urgency_default,
@@ -214,6 +461,7 @@ impl Default for Config {
                     show_duration: Some(10.0),
                     background: Some("#ff6961".to_owned()),
                     foreground_color: Some("#fff".to_owned()),
+                    sticky: Some(false),
                 },
             },
         }
@@ -6,14 +6,7 @@ use services::BatteryManager;
 #[tokio::main]
 async fn main() {
     // this support bluetooth too
-    let manager = BatteryManager::new(|changed| {
-        println!(
-            "Battery charger: {:?}",
-            if changed { "Connected" } else { "Disconnected" }
-        );
-    })
-    .await
-    .unwrap();
+    let manager = BatteryManager::new().await.unwrap();
 
     loop {
         for bat in manager.all() {
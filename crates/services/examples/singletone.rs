@@ -3,7 +3,7 @@ use std::sync::{Arc, Mutex};
 use serde::{Deserialize, Serialize};
 use services::{
     zbus, Battery, Notification, ServiceBroadcast, ServiceManager, ServiceReceive,
-    SingletoneListener,
+    SingletoneListener, Timeout,
 };
 
 #[derive(Default)]
@@ -22,13 +22,15 @@ impl<'a> Notification for App<'a> {
     fn notify(
         &mut self,
         id: u32,
+        _app: Option<String>,
         _summary: String,
         _icon: Option<services::Icon>,
         _urgency: config::Urgency,
         _body: Option<String>,
         _value: Option<f32>,
         _actions: Vec<String>,
-        _expire_timeout: Option<i32>,
+        _expire_timeout: Option<Timeout>,
+        _resident: bool,
     ) -> zbus::fdo::Result<u32> {
         self.notifications.push(id);
         Ok(id)
@@ -0,0 +1,492 @@
+use std::collections::VecDeque;
+use std::env::var_os;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::Result;
+
+use self::protocol::{
+    command, subscription, DeviceInfo, SourceOutputInfo, TagStruct, TagStructWriter, COOKIE_LENGTH,
+    INVALID_INDEX, PROTOCOL_VERSION, VOLUME_NORM,
+};
+
+mod protocol;
+
+/// Audio Device Kind Enum
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum AudioDeviceKind {
+    /// The default sink, speakers or headphones
+    Output,
+    /// The default source, the microphone
+    Input,
+}
+
+/// Audio Device Struct
+#[derive(Clone, Debug)]
+pub struct AudioDevice {
+    pub(crate) kind: AudioDeviceKind,
+    pub(crate) name: Arc<str>,
+    pub(crate) description: Option<Arc<str>>,
+    pub(crate) volume: f32,
+    pub(crate) muted: bool,
+}
+
+impl AudioDevice {
+    pub fn kind(&self) -> AudioDeviceKind {
+        self.kind
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Average volume of the channels, `1.0` is 100% and can be greater
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// Average volume of the channels from 0, can be greater than 100
+    pub fn percent(&self) -> u32 {
+        (self.volume * 100.0).round() as u32
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    fn from_info(kind: AudioDeviceKind, info: &DeviceInfo) -> Self {
+        let channels = info.volume.len().max(1) as f32;
+        let volume = info.volume.iter().map(|v| *v as f32).sum::<f32>() / channels;
+        Self {
+            kind,
+            name: info.name.as_str().into(),
+            description: info.description.as_deref().map(Arc::from),
+            volume: volume / VOLUME_NORM as f32,
+            muted: info.muted,
+        }
+    }
+}
+
+/// Socket of the server, from `PULSE_SERVER` or the user runtime dir
+pub fn default_socket() -> Option<PathBuf> {
+    if let Some(server) = var_os("PULSE_SERVER").and_then(|s| s.into_string().ok()) {
+        if let Some(path) = server.strip_prefix("unix:") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join("pulse/native"))
+}
+
+/// The auth cookie, pipewire-pulse does not check it so zeros are fine as fallback
+fn read_cookie() -> Vec<u8> {
+    let config_dir = var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var_os("HOME").map(|h| PathBuf::from(h).join(".config")));
+    let paths = [
+        var_os("PULSE_COOKIE").map(PathBuf::from),
+        config_dir.map(|c| c.join("pulse/cookie")),
+        var_os("HOME").map(|h| PathBuf::from(h).join(".pulse-cookie")),
+    ];
+
+    paths
+        .into_iter()
+        .flatten()
+        .filter_map(|p| std::fs::read(p).ok())
+        .find(|c| c.len() == COOKIE_LENGTH)
+        .unwrap_or_else(|| vec![0; COOKIE_LENGTH])
+}
+
+/// Audio Manager Struct
+pub struct AudioManager {
+    stream: UnixStream,
+    tag: u32,
+    /// Events received while waiting a reply
+    pending: VecDeque<u32>,
+    output: Option<DeviceInfo>,
+    input: Option<DeviceInfo>,
+}
+
+impl AudioManager {
+    /// Connect to the server and subscribe to the default devices changes
+    pub fn new(socket: impl AsRef<Path>) -> Result<Self> {
+        let mut manager = Self::connect(socket)?;
+        manager.request(command::SUBSCRIBE, |p| {
+            p.put_u32(
+                subscription::MASK_SINK | subscription::MASK_SOURCE | subscription::MASK_SERVER,
+            );
+        })?;
+
+        manager.output = manager.device_info(AudioDeviceKind::Output)?;
+        manager.input = manager.device_info(AudioDeviceKind::Input)?;
+        Ok(manager)
+    }
+
+    /// Connect to the server without following any change, only to query it
+    pub fn connect(socket: impl AsRef<Path>) -> Result<Self> {
+        let mut manager = Self {
+            stream: UnixStream::connect(socket)?,
+            tag: 0,
+            pending: VecDeque::new(),
+            output: None,
+            input: None,
+        };
+
+        manager.request(command::AUTH, |p| {
+            p.put_u32(PROTOCOL_VERSION).put_arbitrary(&read_cookie());
+        })?;
+        manager.request(command::SET_CLIENT_NAME, |p| {
+            p.put_proplist(&[("application.name", env!("CARGO_PKG_NAME"))]);
+        })?;
+        Ok(manager)
+    }
+
+    /// Connect to the server following the applications that start or stop recording
+    pub fn with_capture(socket: impl AsRef<Path>) -> Result<Self> {
+        let mut manager = Self::connect(socket)?;
+        manager.request(command::SUBSCRIBE, |p| {
+            p.put_u32(subscription::MASK_SOURCE_OUTPUT);
+        })?;
+        Ok(manager)
+    }
+
+    /// Names of the applications recording from any source, like the microphone
+    pub fn capture_applications(&mut self) -> Result<Vec<String>> {
+        let Some(mut reply) = self.request(command::GET_SOURCE_OUTPUT_INFO_LIST, |_| {})? else {
+            return Ok(Vec::new());
+        };
+        let mut applications = Vec::new();
+        while !reply.is_empty() {
+            let info = SourceOutputInfo::parse(&mut reply)?;
+            if let Some(application) = info.application.filter(|_| !info.corked) {
+                applications.push(application);
+            }
+        }
+        Ok(applications)
+    }
+
+    pub fn output(&self) -> Option<AudioDevice> {
+        let info = self.output.as_ref()?;
+        Some(AudioDevice::from_info(AudioDeviceKind::Output, info))
+    }
+
+    pub fn input(&self) -> Option<AudioDevice> {
+        let info = self.input.as_ref()?;
+        Some(AudioDevice::from_info(AudioDeviceKind::Input, info))
+    }
+
+    /// Send a command and wait its reply
+    fn request(
+        &mut self,
+        cmd: u32,
+        args: impl FnOnce(&mut TagStructWriter),
+    ) -> Result<Option<TagStruct>> {
+        self.tag = self.tag.wrapping_add(1);
+        let mut packet = TagStructWriter::command(cmd, self.tag);
+        args(&mut packet);
+        packet.send(&mut self.stream)?;
+
+        loop {
+            let mut reply = TagStruct::read(&mut self.stream)?;
+            let cmd = reply.u32()?;
+            let tag = reply.u32()?;
+            match cmd {
+                command::REPLY if tag == self.tag => return Ok(Some(reply)),
+                command::ERROR if tag == self.tag => return Ok(None),
+                command::SUBSCRIBE_EVENT => self.pending.push_back(reply.u32()?),
+                _ => {}
+            }
+        }
+    }
+
+    /// Query the current default device, `None` if there is no one
+    fn device_info(&mut self, kind: AudioDeviceKind) -> Result<Option<DeviceInfo>> {
+        let (cmd, name) = match kind {
+            AudioDeviceKind::Output => (command::GET_SINK_INFO, "@DEFAULT_SINK@"),
+            AudioDeviceKind::Input => (command::GET_SOURCE_INFO, "@DEFAULT_SOURCE@"),
+        };
+        let reply = self.request(cmd, |p| {
+            p.put_u32(INVALID_INDEX).put_string(Some(name));
+        })?;
+
+        Ok(match reply {
+            Some(mut reply) => Some(DeviceInfo::parse(&mut reply)?),
+            None => None,
+        })
+    }
+
+    /// Refresh a device, returns it when the volume or the mute state changes
+    fn refresh(&mut self, kind: AudioDeviceKind) -> Result<Option<AudioDevice>> {
+        let info = self.device_info(kind)?;
+        let last = match kind {
+            AudioDeviceKind::Output => &mut self.output,
+            AudioDeviceKind::Input => &mut self.input,
+        };
+        let old = std::mem::replace(last, info);
+
+        let (Some(old), Some(new)) = (old, last.as_ref()) else {
+            return Ok(None);
+        };
+        // A new default device is not a volume change
+        if old.name != new.name || (old.volume == new.volume && old.muted == new.muted) {
+            return Ok(None);
+        }
+        Ok(Some(AudioDevice::from_info(kind, new)))
+    }
+
+    /// Wait the next event of the subscription, the ones received meanwhile a reply go first
+    fn next_event(&mut self) -> Result<u32> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(event);
+        }
+        loop {
+            let mut packet = TagStruct::read(&mut self.stream)?;
+            if packet.u32()? != command::SUBSCRIBE_EVENT {
+                continue;
+            }
+            _ = packet.u32()?;
+            return Ok(packet.u32()?);
+        }
+    }
+
+    /// Block listening the changes until the server disconnects
+    pub fn listen(&mut self, on_change: impl Fn(&AudioDevice)) -> Result<()> {
+        loop {
+            let event = self.next_event()?;
+            let kinds: &[AudioDeviceKind] = match event & subscription::FACILITY_MASK {
+                subscription::FACILITY_SINK => &[AudioDeviceKind::Output],
+                subscription::FACILITY_SOURCE => &[AudioDeviceKind::Input],
+                // The default devices could be changed
+                subscription::FACILITY_SERVER => &[AudioDeviceKind::Output, AudioDeviceKind::Input],
+                _ => &[],
+            };
+            for kind in kinds {
+                if let Some(device) = self.refresh(*kind)? {
+                    on_change(&device);
+                }
+            }
+        }
+    }
+
+    /// Block calling `on_change` each time a recording is started, stopped or paused, until the
+    /// server disconnects
+    pub fn listen_capture(&mut self, on_change: impl Fn()) -> Result<()> {
+        loop {
+            if self.next_event()? & subscription::FACILITY_MASK
+                == subscription::FACILITY_SOURCE_OUTPUT
+            {
+                on_change();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixListener;
+    use std::process::{Child, Command, Stdio};
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn socket_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("sosd-{name}-{}", std::process::id()));
+        _ = std::fs::remove_file(&path);
+        _ = std::fs::remove_dir_all(&path);
+        path
+    }
+
+    fn reply(stream: &mut UnixStream, tag: u32, args: impl FnOnce(&mut TagStructWriter)) {
+        let mut packet = TagStructWriter::command(command::REPLY, tag);
+        args(&mut packet);
+        packet.send(stream).unwrap();
+    }
+
+    fn sink_info(packet: &mut TagStructWriter, volume: u32, muted: bool) {
+        packet
+            .put_u32(0)
+            .put_string(Some("auto_null"))
+            .put_string(Some("Dummy Output"))
+            .put_u32(0)
+            .put_u32(0)
+            .put_u32(INVALID_INDEX)
+            .put_cvolume(&[volume, volume])
+            .put_bool(muted);
+    }
+
+    /// Answer like a server with one sink whose volume changes after subscribing
+    fn fake_server(listener: UnixListener) {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut volume = VOLUME_NORM / 2;
+        let mut sink_queries = 0;
+
+        while let Ok(mut packet) = TagStruct::read(&mut stream) {
+            let cmd = packet.u32().unwrap();
+            let tag = packet.u32().unwrap();
+            match cmd {
+                command::AUTH => reply(&mut stream, tag, |p| {
+                    p.put_u32(PROTOCOL_VERSION);
+                }),
+                command::SET_CLIENT_NAME => reply(&mut stream, tag, |p| {
+                    p.put_u32(1);
+                }),
+                command::GET_SINK_INFO => {
+                    sink_queries += 1;
+                    reply(&mut stream, tag, |p| sink_info(p, volume, false));
+                }
+                // Without microphone
+                command::GET_SOURCE_INFO => {
+                    let mut packet = TagStructWriter::command(command::ERROR, tag);
+                    packet.put_u32(5);
+                    packet.send(&mut stream).unwrap();
+                }
+                _ => reply(&mut stream, tag, |_| {}),
+            }
+
+            // The initial state is known, change the volume
+            if cmd == command::GET_SOURCE_INFO && sink_queries == 1 {
+                volume = VOLUME_NORM / 4 * 3;
+                let mut event = TagStructWriter::command(command::SUBSCRIBE_EVENT, u32::MAX);
+                event
+                    .put_u32(0x0010 | subscription::FACILITY_SINK)
+                    .put_u32(0);
+                event.send(&mut stream).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_listen_fake_server() {
+        let path = socket_path("pulse-fake");
+        let listener = UnixListener::bind(&path).unwrap();
+        std::thread::spawn(move || fake_server(listener));
+
+        let mut manager = AudioManager::new(&path).unwrap();
+        assert_eq!(manager.output().unwrap().percent(), 50);
+        assert!(manager.input().is_none());
+
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            _ = manager.listen(|device| _ = tx.send(device.clone()));
+        });
+        let device = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(device.kind(), AudioDeviceKind::Output);
+        assert_eq!(device.description(), Some("Dummy Output"));
+        assert_eq!(device.percent(), 75);
+        _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_listen_capture() {
+        let path = socket_path("pulse-capture");
+        let listener = UnixListener::bind(&path).unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            while let Ok(mut packet) = TagStruct::read(&mut stream) {
+                let cmd = packet.u32().unwrap();
+                let tag = packet.u32().unwrap();
+                reply(&mut stream, tag, |_| {});
+                if cmd != command::SUBSCRIBE {
+                    continue;
+                }
+                // Only the recording streams matter, then the server goes away
+                for event in [
+                    0x0010 | subscription::FACILITY_SINK,
+                    subscription::FACILITY_SOURCE_OUTPUT,
+                    0x0020 | subscription::FACILITY_SOURCE_OUTPUT,
+                ] {
+                    let mut packet = TagStructWriter::command(command::SUBSCRIBE_EVENT, u32::MAX);
+                    packet.put_u32(event).put_u32(3);
+                    packet.send(&mut stream).unwrap();
+                }
+                return;
+            }
+        });
+
+        let mut manager = AudioManager::with_capture(&path).unwrap();
+        let (tx, rx) = channel();
+        assert!(manager.listen_capture(|| _ = tx.send(())).is_err());
+        assert_eq!(rx.try_iter().count(), 2);
+        _ = std::fs::remove_file(path);
+    }
+
+    /// Pulseaudio server of a test, killed even when the test fails
+    struct NullSinkServer {
+        child: Child,
+        dir: PathBuf,
+    }
+
+    impl Drop for NullSinkServer {
+        fn drop(&mut self) {
+            _ = self.child.kill();
+            _ = self.child.wait();
+            _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// Runs against a real server with a null sink, skipped when pulseaudio or pactl are not
+    /// installed or the server does not start, like when running as root
+    #[test]
+    fn test_listen_null_sink_server() {
+        let dir = socket_path("pulse-null");
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("native");
+        let child = Command::new("pulseaudio")
+            .args([
+                "-n",
+                "--daemonize=no",
+                "--exit-idle-time=-1",
+                "--use-pid-file=no",
+            ])
+            .arg(format!(
+                "--load=module-native-protocol-unix auth-anonymous=1 socket={}",
+                socket.display()
+            ))
+            .arg("--load=module-null-sink")
+            .env("HOME", &dir)
+            .env("XDG_RUNTIME_DIR", &dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(child) = child else {
+            eprintln!("pulseaudio not found, skipping");
+            _ = std::fs::remove_dir_all(dir);
+            return;
+        };
+        let mut server = NullSinkServer { child, dir };
+
+        let manager = (0..50).find_map(|_| {
+            std::thread::sleep(Duration::from_millis(100));
+            if let Ok(Some(_)) = server.child.try_wait() {
+                return Some(None);
+            }
+            AudioManager::new(&socket).ok().map(Some)
+        });
+        let Some(Some(mut manager)) = manager else {
+            eprintln!("pulseaudio did not start, skipping");
+            return;
+        };
+        assert_eq!(manager.output().unwrap().name(), "null");
+
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            _ = manager.listen(|device| _ = tx.send(device.clone()));
+        });
+        let muted = Command::new("pactl")
+            .arg(format!("--server=unix:{}", socket.display()))
+            .args(["set-sink-mute", "@DEFAULT_SINK@", "1"])
+            .status();
+        if muted.is_err() {
+            eprintln!("pactl not found, skipping");
+            return;
+        }
+
+        let device = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(device.muted());
+    }
+}
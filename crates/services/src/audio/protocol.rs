@@ -0,0 +1,450 @@
+//! Minimal client side of the PulseAudio native protocol, enough to follow the
+//! volume of the default devices. pipewire-pulse speaks the same protocol.
+//!
+//! https://gitlab.freedesktop.org/pulseaudio/pulseaudio/-/blob/master/src/pulsecore/native-common.h
+//! https://gitlab.freedesktop.org/pulseaudio/pulseaudio/-/blob/master/src/pulsecore/tagstruct.h
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+pub const PROTOCOL_VERSION: u32 = 32;
+pub const COOKIE_LENGTH: usize = 256;
+pub const VOLUME_NORM: u32 = 0x10000;
+pub const INVALID_INDEX: u32 = u32::MAX;
+/// Channel used by the control packets, the other ones carry audio
+const COMMAND_CHANNEL: u32 = u32::MAX;
+const DESCRIPTOR_SIZE: usize = 20;
+
+pub mod command {
+    pub const ERROR: u32 = 0;
+    pub const REPLY: u32 = 2;
+    pub const AUTH: u32 = 8;
+    pub const SET_CLIENT_NAME: u32 = 9;
+    pub const GET_SINK_INFO: u32 = 21;
+    pub const GET_SOURCE_INFO: u32 = 23;
+    pub const GET_SOURCE_OUTPUT_INFO_LIST: u32 = 32;
+    pub const SUBSCRIBE: u32 = 35;
+    pub const SUBSCRIBE_EVENT: u32 = 66;
+}
+
+pub mod subscription {
+    pub const MASK_SINK: u32 = 0x0001;
+    pub const MASK_SOURCE: u32 = 0x0002;
+    pub const MASK_SOURCE_OUTPUT: u32 = 0x0008;
+    pub const MASK_SERVER: u32 = 0x0080;
+
+    pub const FACILITY_MASK: u32 = 0x000F;
+    pub const FACILITY_SINK: u32 = 0x0000;
+    pub const FACILITY_SOURCE: u32 = 0x0001;
+    pub const FACILITY_SOURCE_OUTPUT: u32 = 0x0006;
+    pub const FACILITY_SERVER: u32 = 0x0007;
+}
+
+mod tag {
+    pub const STRING: u8 = b't';
+    pub const STRING_NULL: u8 = b'N';
+    pub const U32: u8 = b'L';
+    pub const U8: u8 = b'B';
+    pub const U64: u8 = b'R';
+    pub const S64: u8 = b'r';
+    pub const SAMPLE_SPEC: u8 = b'a';
+    pub const ARBITRARY: u8 = b'x';
+    pub const BOOLEAN_TRUE: u8 = b'1';
+    pub const BOOLEAN_FALSE: u8 = b'0';
+    pub const TIMEVAL: u8 = b'T';
+    pub const USEC: u8 = b'U';
+    pub const CHANNEL_MAP: u8 = b'm';
+    pub const CVOLUME: u8 = b'v';
+    pub const PROPLIST: u8 = b'P';
+    pub const VOLUME: u8 = b'V';
+    pub const FORMAT_INFO: u8 = b'f';
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Serializer of the typed values of a packet
+#[derive(Debug, Default)]
+pub struct TagStructWriter(Vec<u8>);
+
+impl TagStructWriter {
+    pub fn command(command: u32, tag: u32) -> Self {
+        let mut writer = Self::default();
+        writer.put_u32(command);
+        writer.put_u32(tag);
+        writer
+    }
+
+    pub fn put_u32(&mut self, value: u32) -> &mut Self {
+        self.0.push(tag::U32);
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    #[cfg(test)]
+    pub fn put_bool(&mut self, value: bool) -> &mut Self {
+        self.0.push(if value {
+            tag::BOOLEAN_TRUE
+        } else {
+            tag::BOOLEAN_FALSE
+        });
+        self
+    }
+
+    pub fn put_string(&mut self, value: Option<&str>) -> &mut Self {
+        match value {
+            Some(value) => {
+                self.0.push(tag::STRING);
+                self.0.extend_from_slice(value.as_bytes());
+                self.0.push(0);
+            }
+            None => self.0.push(tag::STRING_NULL),
+        }
+        self
+    }
+
+    pub fn put_arbitrary(&mut self, value: &[u8]) -> &mut Self {
+        self.0.push(tag::ARBITRARY);
+        self.0
+            .extend_from_slice(&(value.len() as u32).to_be_bytes());
+        self.0.extend_from_slice(value);
+        self
+    }
+
+    #[cfg(test)]
+    pub fn put_cvolume(&mut self, volumes: &[u32]) -> &mut Self {
+        self.0.push(tag::CVOLUME);
+        self.0.push(volumes.len() as u8);
+        for volume in volumes {
+            self.0.extend_from_slice(&volume.to_be_bytes());
+        }
+        self
+    }
+
+    /// Proplist values are arbitrary data, strings must include the nul byte
+    pub fn put_proplist(&mut self, props: &[(&str, &str)]) -> &mut Self {
+        self.0.push(tag::PROPLIST);
+        for (key, value) in props {
+            self.put_string(Some(key));
+            let value = [value.as_bytes(), &[0]].concat();
+            self.put_u32(value.len() as u32);
+            self.put_arbitrary(&value);
+        }
+        self.put_string(None)
+    }
+
+    /// Write the packet with its descriptor
+    pub fn send(&self, stream: &mut impl Write) -> io::Result<()> {
+        let mut packet = Vec::with_capacity(DESCRIPTOR_SIZE + self.0.len());
+        packet.extend_from_slice(&(self.0.len() as u32).to_be_bytes());
+        packet.extend_from_slice(&COMMAND_CHANNEL.to_be_bytes());
+        packet.extend_from_slice(&[0; 12]);
+        packet.extend_from_slice(&self.0);
+        stream.write_all(&packet)
+    }
+}
+
+/// Deserializer of the typed values of a packet
+#[derive(Debug)]
+pub struct TagStruct {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl TagStruct {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Read the next command packet, audio packets are skipped
+    pub fn read(stream: &mut impl Read) -> io::Result<Self> {
+        loop {
+            let mut descriptor = [0; DESCRIPTOR_SIZE];
+            stream.read_exact(&mut descriptor)?;
+            let length = u32::from_be_bytes(descriptor[0..4].try_into().unwrap()) as usize;
+            let channel = u32::from_be_bytes(descriptor[4..8].try_into().unwrap());
+
+            let mut data = vec![0; length];
+            stream.read_exact(&mut data)?;
+            if channel == COMMAND_CHANNEL {
+                return Ok(Self::new(data));
+            }
+        }
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&[u8]> {
+        let end = self.pos + len;
+        let bytes = self
+            .data
+            .get(self.pos..end)
+            .ok_or_else(|| invalid("Packet too short"))?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn expect(&mut self, expected: u8) -> io::Result<()> {
+        if self.take(1)?[0] != expected {
+            return Err(invalid("Unexpected tag"));
+        }
+        Ok(())
+    }
+
+    fn raw_u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn raw_string(&mut self) -> io::Result<String> {
+        let rest = &self.data[self.pos..];
+        let len = rest
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| invalid("Unterminated string"))?;
+        let value = String::from_utf8_lossy(&rest[..len]).into_owned();
+        self.pos += len + 1;
+        Ok(value)
+    }
+
+    pub fn u32(&mut self) -> io::Result<u32> {
+        self.expect(tag::U32)?;
+        self.raw_u32()
+    }
+
+    pub fn bool(&mut self) -> io::Result<bool> {
+        match self.take(1)?[0] {
+            tag::BOOLEAN_TRUE => Ok(true),
+            tag::BOOLEAN_FALSE => Ok(false),
+            _ => Err(invalid("Unexpected tag")),
+        }
+    }
+
+    pub fn string(&mut self) -> io::Result<Option<String>> {
+        match self.take(1)?[0] {
+            tag::STRING => self.raw_string().map(Some),
+            tag::STRING_NULL => Ok(None),
+            _ => Err(invalid("Unexpected tag")),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    /// Proplist values are arbitrary data, only the strings ones are kept
+    pub fn proplist(&mut self) -> io::Result<HashMap<String, String>> {
+        self.expect(tag::PROPLIST)?;
+        let mut props = HashMap::new();
+        while let Some(key) = self.string()? {
+            let len = self.u32()? as usize;
+            self.expect(tag::ARBITRARY)?;
+            if self.raw_u32()? as usize != len {
+                return Err(invalid("Proplist length mismatch"));
+            }
+            if let Some(value) = self.take(len)?.strip_suffix(&[0]) {
+                props.insert(key, String::from_utf8_lossy(value).into_owned());
+            }
+        }
+        Ok(props)
+    }
+
+    pub fn cvolume(&mut self) -> io::Result<Vec<u32>> {
+        self.expect(tag::CVOLUME)?;
+        let channels = self.take(1)?[0];
+        (0..channels).map(|_| self.raw_u32()).collect()
+    }
+
+    /// Skip the next value whatever its type is
+    pub fn skip(&mut self) -> io::Result<()> {
+        let tag = self.take(1)?[0];
+        let len = match tag {
+            tag::STRING => return self.raw_string().map(|_| ()),
+            tag::STRING_NULL | tag::BOOLEAN_TRUE | tag::BOOLEAN_FALSE => 0,
+            tag::U8 => 1,
+            tag::U32 | tag::VOLUME => 4,
+            tag::U64 | tag::S64 | tag::USEC | tag::TIMEVAL => 8,
+            tag::SAMPLE_SPEC => 6,
+            tag::ARBITRARY => self.raw_u32()? as usize,
+            tag::CHANNEL_MAP => self.take(1)?[0] as usize,
+            tag::CVOLUME => self.take(1)?[0] as usize * 4,
+            tag::PROPLIST => {
+                while self.string()?.is_some() {
+                    self.skip()?;
+                    self.skip()?;
+                }
+                0
+            }
+            tag::FORMAT_INFO => {
+                self.skip()?;
+                self.skip()?;
+                0
+            }
+            _ => return Err(invalid("Unknown tag")),
+        };
+        self.take(len).map(|_| ())
+    }
+}
+
+/// Volume and mute state of a sink or source
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceInfo {
+    pub index: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub volume: Vec<u32>,
+    pub muted: bool,
+}
+
+impl DeviceInfo {
+    /// Parse the common beginning of the sink and source info replies
+    pub fn parse(reply: &mut TagStruct) -> io::Result<Self> {
+        let index = reply.u32()?;
+        let name = reply.string()?.unwrap_or_default();
+        let description = reply.string()?;
+        // sample spec, channel map and owner module
+        for _ in 0..3 {
+            reply.skip()?;
+        }
+        let volume = reply.cvolume()?;
+        let muted = reply.bool()?;
+
+        Ok(Self {
+            index,
+            name,
+            description,
+            volume,
+            muted,
+        })
+    }
+}
+
+/// A recording stream, like an application capturing the microphone
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceOutputInfo {
+    pub index: u32,
+    /// `application.name` of the proplist
+    pub application: Option<String>,
+    /// Paused streams are not capturing
+    pub corked: bool,
+}
+
+impl SourceOutputInfo {
+    /// Parse an entry of the source output info list reply
+    pub fn parse(reply: &mut TagStruct) -> io::Result<Self> {
+        let index = reply.u32()?;
+        // name, owner module, client, source, sample spec, channel map,
+        // buffer and source latency, resample method and driver
+        for _ in 0..10 {
+            reply.skip()?;
+        }
+        let application = reply.proplist()?.remove("application.name");
+        let corked = reply.bool()?;
+        // volume, muted, has volume, volume writable and format
+        for _ in 0..5 {
+            reply.skip()?;
+        }
+
+        Ok(Self {
+            index,
+            application,
+            corked,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packet_roundtrip() {
+        let mut packet = TagStructWriter::command(command::SUBSCRIBE, 7);
+        packet.put_u32(subscription::MASK_SINK);
+        let mut raw = Vec::new();
+        packet.send(&mut raw).unwrap();
+
+        assert_eq!(&raw[0..4], &15u32.to_be_bytes());
+        let mut read = TagStruct::read(&mut raw.as_slice()).unwrap();
+        assert_eq!(read.u32().unwrap(), command::SUBSCRIBE);
+        assert_eq!(read.u32().unwrap(), 7);
+        assert_eq!(read.u32().unwrap(), subscription::MASK_SINK);
+        assert!(read.u32().is_err());
+    }
+
+    #[test]
+    fn test_parse_sink_info() {
+        let mut reply = TagStructWriter::default();
+        reply
+            .put_u32(0)
+            .put_string(Some("auto_null"))
+            .put_string(Some("Dummy Output"));
+        // sample spec s16le, 2 channels, 44100 Hz
+        reply
+            .0
+            .extend_from_slice(&[tag::SAMPLE_SPEC, 3, 2, 0, 0, 0xac, 0x44]);
+        reply.0.extend_from_slice(&[tag::CHANNEL_MAP, 2, 1, 2]);
+        reply
+            .put_u32(INVALID_INDEX)
+            .put_cvolume(&[VOLUME_NORM / 2, VOLUME_NORM / 2])
+            .put_bool(true)
+            .put_proplist(&[("device.class", "abstract")]);
+
+        let info = DeviceInfo::parse(&mut TagStruct::new(reply.0)).unwrap();
+        assert_eq!(info.name, "auto_null");
+        assert_eq!(info.description.as_deref(), Some("Dummy Output"));
+        assert_eq!(info.volume, [VOLUME_NORM / 2; 2]);
+        assert!(info.muted);
+    }
+
+    #[test]
+    fn test_parse_source_outputs() {
+        let mut reply = TagStructWriter::default();
+        for (index, application, corked) in [(3, "Firefox", false), (4, "obs", true)] {
+            reply
+                .put_u32(index)
+                .put_string(Some("Capture"))
+                .put_u32(INVALID_INDEX)
+                .put_u32(7)
+                .put_u32(1);
+            reply
+                .0
+                .extend_from_slice(&[tag::SAMPLE_SPEC, 3, 1, 0, 0, 0xbb, 0x80]);
+            reply.0.extend_from_slice(&[tag::CHANNEL_MAP, 1, 0]);
+            for _ in 0..2 {
+                reply.0.push(tag::USEC);
+                reply.0.extend_from_slice(&0u64.to_be_bytes());
+            }
+            reply
+                .put_string(None)
+                .put_string(Some("protocol-native.c"))
+                .put_proplist(&[("application.name", application), ("media.name", "Mic")])
+                .put_bool(corked)
+                .put_cvolume(&[VOLUME_NORM])
+                .put_bool(false)
+                .put_bool(true)
+                .put_bool(true);
+            reply.0.extend_from_slice(&[tag::FORMAT_INFO, tag::U8, 1]);
+            reply.put_proplist(&[]);
+        }
+
+        let mut read = TagStruct::new(reply.0);
+        let first = SourceOutputInfo::parse(&mut read).unwrap();
+        assert_eq!(first.index, 3);
+        assert_eq!(first.application.as_deref(), Some("Firefox"));
+        assert!(!first.corked);
+        let second = SourceOutputInfo::parse(&mut read).unwrap();
+        assert_eq!(second.application.as_deref(), Some("obs"));
+        assert!(second.corked);
+        assert!(read.is_empty());
+    }
+
+    #[test]
+    fn test_skip_proplist() {
+        let mut data = TagStructWriter::default();
+        data.put_proplist(&[("application.name", "sosd"), ("a", "b")])
+            .put_u32(42);
+
+        let mut read = TagStruct::new(data.0);
+        read.skip().unwrap();
+        assert_eq!(read.u32().unwrap(), 42);
+    }
+}
@@ -1,12 +1,14 @@
 use parking_lot::RwLock;
+use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use zbus::Connection;
 
 use crate::Result;
 
 use self::bluez::get_bluez_batteries;
-use self::sys::{get_batteries, get_charger};
+use self::sys::{get_batteries, get_charger, POWER_SUPPLY_PATH};
 
 mod bluez;
 mod sys;
@@ -30,6 +32,14 @@ pub struct Battery {
     pub(crate) name: Arc<str>,
     pub(crate) state: BatteryState,
     pub(crate) path: Option<PathBuf>,
+    /// Remaining energy, in the unit reported by the driver
+    pub(crate) energy_now: Option<u64>,
+    /// Energy when full, in the same unit as `energy_now`
+    pub(crate) energy_full: Option<u64>,
+    /// Energy when full as designed by the vendor, in the same unit as `energy_now`
+    pub(crate) energy_full_design: Option<u64>,
+    /// Charge or discharge rate, in the unit of `energy_now` per hour
+    pub(crate) power: Option<u64>,
 }
 
 impl Battery {
@@ -48,23 +58,90 @@ impl Battery {
     pub fn state(&self) -> BatteryState {
         self.state
     }
+
+    /// Estimated time until the battery is empty, only while discharging
+    pub fn time_to_empty(&self) -> Option<Duration> {
+        if self.state != BatteryState::Discharging {
+            return None;
+        }
+        hours_to_duration(self.energy_now?, self.power?)
+    }
+
+    /// Estimated time until the battery is full, only while charging
+    pub fn time_to_full(&self) -> Option<Duration> {
+        if self.state != BatteryState::Charging {
+            return None;
+        }
+        let missing = self.energy_full?.saturating_sub(self.energy_now?);
+        hours_to_duration(missing, self.power?)
+    }
+
+    /// Capacity left compared with the design capacity, from 0 to 100
+    pub fn health(&self) -> Option<u8> {
+        let design = self.energy_full_design.filter(|d| *d > 0)?;
+        Some(((self.energy_full? * 100) / design).min(100) as u8)
+    }
+}
+
+fn hours_to_duration(energy: u64, power: u64) -> Option<Duration> {
+    if power == 0 {
+        return None;
+    }
+    let secs = (energy as f64 / power as f64) * 3600.0;
+    Some(Duration::from_secs(secs.round() as u64))
+}
+
+/// Last rate samples of one battery, used to smooth the time estimations
+#[derive(Debug, Default)]
+struct RateHistory {
+    state: Option<BatteryState>,
+    samples: VecDeque<u64>,
+}
+
+impl RateHistory {
+    const MAX_SAMPLES: usize = 8;
+
+    /// Push a new sample and return the smoothed rate
+    fn push(&mut self, state: BatteryState, rate: u64) -> u64 {
+        // Charge and discharge rates are not comparable
+        if self.state != Some(state) {
+            self.state = Some(state);
+            self.samples.clear();
+        }
+        if self.samples.len() == Self::MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(rate);
+
+        self.samples.iter().sum::<u64>() / self.samples.len() as u64
+    }
 }
 
 /// Battery Manager Struct
 pub struct BatteryManager {
+    root: PathBuf,
     batteries: RwLock<Vec<Battery>>,
-    connection: Connection,
+    rates: RwLock<HashMap<Arc<str>, RateHistory>>,
+    /// Bus of BlueZ, without it only the batteries of the sysfs root are read
+    connection: Option<Connection>,
 }
 
 impl BatteryManager {
     /// Create a new BatteryManager
-    pub async fn new(on_charger: impl Fn(bool) + Send + Sync + 'static) -> Result<Self> {
-        let batteries = RwLock::new(Vec::with_capacity(4));
-        let on_charger = Arc::new(on_charger);
+    pub async fn new() -> Result<Self> {
+        Self::with_root(POWER_SUPPLY_PATH, Some(Connection::system().await?)).await
+    }
 
+    /// Create a BatteryManager reading the power supplies under `root`
+    pub async fn with_root(
+        root: impl Into<PathBuf>,
+        connection: Option<Connection>,
+    ) -> Result<Self> {
         let manager = BatteryManager {
-            batteries,
-            connection: Connection::system().await?,
+            batteries: RwLock::new(Vec::with_capacity(4)),
+            root: root.into(),
+            rates: RwLock::new(HashMap::with_capacity(4)),
+            connection,
         };
 
         manager.refresh().await?;
@@ -77,10 +154,28 @@ impl BatteryManager {
 
     /// Refresh battery states
     pub async fn refresh(&self) -> Result<()> {
-        let mut batteries = get_batteries()?;
+        let mut batteries = get_batteries(&self.root)?;
 
-        if let Ok(bluez_bats) = get_bluez_batteries(&self.connection).await {
-            batteries.extend(bluez_bats);
+        {
+            let mut rates = self.rates.write();
+            for battery in batteries.iter_mut() {
+                let Some(power) = battery.power else {
+                    continue;
+                };
+                let key = battery
+                    .path()
+                    .and_then(Path::to_str)
+                    .map(Arc::from)
+                    .unwrap_or_else(|| battery.name.clone());
+                let smoothed = rates.entry(key).or_default().push(battery.state, power);
+                battery.power = Some(smoothed);
+            }
+        }
+
+        if let Some(connection) = self.connection.as_ref() {
+            if let Ok(bluez_bats) = get_bluez_batteries(connection).await {
+                batteries.extend(bluez_bats);
+            }
         }
 
         batteries.sort_by_key(|b| b.level());
@@ -109,3 +204,56 @@ impl BatteryManager {
             .cloned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_history_smoothing() {
+        let mut history = RateHistory::default();
+
+        assert_eq!(
+            history.push(BatteryState::Discharging, 8_000_000),
+            8_000_000
+        );
+        assert_eq!(
+            history.push(BatteryState::Discharging, 4_000_000),
+            6_000_000
+        );
+        for _ in 0..RateHistory::MAX_SAMPLES {
+            history.push(BatteryState::Discharging, 5_000_000);
+        }
+        assert_eq!(history.samples.len(), RateHistory::MAX_SAMPLES);
+        assert_eq!(
+            history.push(BatteryState::Discharging, 5_000_000),
+            5_000_000
+        );
+    }
+
+    #[test]
+    fn test_rate_history_resets_on_state_change() {
+        let mut history = RateHistory::default();
+
+        history.push(BatteryState::Discharging, 8_000_000);
+        assert_eq!(history.push(BatteryState::Charging, 30_000_000), 30_000_000);
+        assert_eq!(history.samples.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_manager_fixture_root() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/power_supply");
+        let manager = BatteryManager::with_root(root, None).await.unwrap();
+        manager.refresh().await.unwrap();
+
+        let levels = manager.all().iter().map(Battery::level).collect::<Vec<_>>();
+        assert_eq!(levels, [42, 50]);
+
+        let low = manager.batteries_below(45);
+        assert_eq!(low.len(), 1);
+        assert_eq!(low[0].time_to_empty(), Some(Duration::from_secs(3 * 3600)));
+
+        let charging = manager.battery_by_name("DELL 1VX1H").unwrap();
+        assert_eq!(charging.time_to_full(), Some(Duration::from_secs(2 * 3600)));
+    }
+}
@@ -44,6 +44,10 @@ pub async fn get_bluez_batteries(conn: &Connection) -> Result<Vec<Battery>, Erro
                 state: BatteryState::Discharging,
                 // not have Path, right?
                 path: None,
+                energy_now: None,
+                energy_full: None,
+                energy_full_design: None,
+                power: None,
             })
         })
         .collect::<Vec<_>>())
@@ -4,6 +4,9 @@ use std::path::Path;
 
 use crate::{Battery, BatteryState, Error, Result};
 
+/// Default sysfs root where the kernel exposes power supplies
+pub const POWER_SUPPLY_PATH: &str = "/sys/class/power_supply";
+
 /// Read a battery file
 fn read_battery_file(dir: &Path, file: &str) -> Result<String> {
     let mut content = fs::read_to_string(dir.join(file))?;
@@ -29,7 +32,10 @@ fn name_to_battery_state(name: &str) -> Result<BatteryState> {
 
 /// Read battery energy or charge file
 fn read_battery_file_energy_or_charge(dir: &Path, partial_file: &str) -> Result<u64> {
-    if let Ok(value) = read_battery_file(dir, &format!("energy_{}", partial_file))?.parse() {
+    if let Some(value) = read_battery_file(dir, &format!("energy_{}", partial_file))
+        .ok()
+        .and_then(|v| v.parse().ok())
+    {
         return Ok(value);
     }
 
@@ -38,6 +44,22 @@ fn read_battery_file_energy_or_charge(dir: &Path, partial_file: &str) -> Result<
     Ok((charge * voltage) / 1000)
 }
 
+/// Read battery power or current file, in the same unit scale as
+/// [`read_battery_file_energy_or_charge`] so both can be divided
+fn read_battery_file_power_or_current(dir: &Path) -> Result<u64> {
+    if let Some(value) = read_battery_file(dir, "power_now")
+        .ok()
+        .and_then(|v| v.parse().ok())
+    {
+        return Ok(value);
+    }
+
+    let voltage: u64 = read_battery_file(dir, "voltage_now")?.parse()?;
+    // Some drivers report negative current while discharging
+    let current: i64 = read_battery_file(dir, "current_now")?.parse()?;
+    Ok((current.unsigned_abs() * voltage) / 1000)
+}
+
 /// Read battery directory and create a Battery
 fn read_battery_dir(dir: &Path) -> Result<Battery> {
     let now_uwh = read_battery_file_energy_or_charge(dir, "now")?;
@@ -51,12 +73,18 @@ fn read_battery_dir(dir: &Path) -> Result<Battery> {
             .unwrap_or_else(|_| "Unknown".to_owned())
             .into(),
         state: name_to_battery_state(&read_battery_file(dir, "status")?)?,
+        energy_now: Some(now_uwh),
+        energy_full: Some(full_uwh),
+        energy_full_design: read_battery_file_energy_or_charge(dir, "full_design").ok(),
+        power: read_battery_file_power_or_current(dir)
+            .ok()
+            .filter(|p| *p > 0),
     })
 }
 
 /// Get all batteries
-pub fn get_batteries() -> Result<Vec<Battery>> {
-    let batteries = fs::read_dir("/sys/class/power_supply")?
+pub fn get_batteries(root: &Path) -> Result<Vec<Battery>> {
+    let batteries = fs::read_dir(root)?
         .filter_map(|entry| entry.ok())
         .map(|e| e.path())
         .filter(|p| {
@@ -71,8 +99,8 @@ pub fn get_batteries() -> Result<Vec<Battery>> {
     Ok(batteries)
 }
 
-pub fn get_charger() -> Result<bool> {
-    let chargers = fs::read_dir("/sys/class/power_supply")?
+pub fn get_charger(root: &Path) -> Result<bool> {
+    let chargers = fs::read_dir(root)?
         .filter_map(|entry| entry.ok())
         .map(|e| e.path())
         .filter(|p| {
@@ -86,3 +114,55 @@ pub fn get_charger() -> Result<bool> {
 
     Ok(chargers.iter().any(|c| c.trim() == "1"))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn test_read_energy_battery() {
+        let bat = read_battery_dir(&fixture("power_supply/BAT0")).unwrap();
+
+        assert_eq!(bat.name(), "5B10W51867");
+        assert_eq!(bat.state(), BatteryState::Discharging);
+        assert_eq!(bat.level(), 42);
+        assert_eq!(bat.health(), Some(88));
+        // 21.0672 Wh at 7.0224 W
+        assert_eq!(bat.time_to_empty(), Some(Duration::from_secs(3 * 3600)));
+        assert_eq!(bat.time_to_full(), None);
+    }
+
+    #[test]
+    fn test_read_charge_battery() {
+        let bat = read_battery_dir(&fixture("power_supply/BAT1")).unwrap();
+
+        assert_eq!(bat.state(), BatteryState::Charging);
+        assert_eq!(bat.level(), 50);
+        assert_eq!(bat.health(), Some(80));
+        assert_eq!(bat.time_to_empty(), None);
+        // 1.6 Ah missing at 0.8 A
+        assert_eq!(bat.time_to_full(), Some(Duration::from_secs(2 * 3600)));
+    }
+
+    #[test]
+    fn test_get_batteries_and_charger() {
+        let root = fixture("power_supply");
+        let mut batteries = get_batteries(&root).unwrap();
+        batteries.sort_by_key(|b| b.level());
+
+        assert_eq!(
+            batteries.iter().map(Battery::level).collect::<Vec<_>>(),
+            [42, 50]
+        );
+        assert!(get_charger(&root).unwrap());
+    }
+}
@@ -0,0 +1,289 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use config::BrightnessSubsystem;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify, WatchDescriptor};
+use parking_lot::RwLock;
+use zbus::Connection;
+
+use crate::Result;
+
+/// Default sysfs root where the kernel exposes backlights and leds
+pub const SYSFS_CLASS_PATH: &str = "/sys/class";
+
+/// Files touched by the kernel or by writers when the brightness changes
+const WATCHED_FILES: [&str; 3] = ["brightness", "actual_brightness", "brightness_hw_changed"];
+
+/// Brightness Device Struct
+#[derive(Clone, Debug)]
+pub struct BrightnessDevice {
+    pub(crate) name: Arc<str>,
+    pub(crate) subsystem: BrightnessSubsystem,
+    pub(crate) brightness: u32,
+    pub(crate) max_brightness: u32,
+}
+
+impl BrightnessDevice {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn subsystem(&self) -> BrightnessSubsystem {
+        self.subsystem
+    }
+
+    pub fn brightness(&self) -> u32 {
+        self.brightness
+    }
+
+    pub fn max_brightness(&self) -> u32 {
+        self.max_brightness
+    }
+
+    /// Current brightness from 0 to 100
+    pub fn percent(&self) -> u8 {
+        if self.max_brightness == 0 {
+            return 0;
+        }
+        ((self.brightness as u64 * 100) / self.max_brightness as u64).min(100) as u8
+    }
+
+    /// Raw brightness for the `percent`, at least one step away from the current one when the
+    /// percent changes, a device with a few steps would not move otherwise
+    pub fn brightness_for(&self, percent: u8) -> u32 {
+        let percent = percent.min(100);
+        let brightness = ((percent as u64 * self.max_brightness as u64) / 100) as u32;
+        match percent.cmp(&self.percent()) {
+            Ordering::Greater => brightness.max(self.brightness + 1).min(self.max_brightness),
+            Ordering::Less => brightness.min(self.brightness.saturating_sub(1)),
+            Ordering::Equal => self.brightness,
+        }
+    }
+}
+
+fn read_u32(dir: &Path, file: &str) -> Result<u32> {
+    Ok(fs::read_to_string(dir.join(file))?.trim().parse()?)
+}
+
+/// Read a device directory, `actual_brightness` is preferred because is what the hardware shows
+fn read_device_dir(dir: &Path, subsystem: BrightnessSubsystem) -> Result<BrightnessDevice> {
+    let brightness = read_u32(dir, "actual_brightness").or_else(|_| read_u32(dir, "brightness"))?;
+    Ok(BrightnessDevice {
+        subsystem,
+        brightness,
+        max_brightness: read_u32(dir, "max_brightness")?,
+        name: dir
+            .file_name()
+            .and_then(OsStr::to_str)
+            .unwrap_or("Unknown")
+            .into(),
+    })
+}
+
+/// Leds are used for a lot of things, only the backlights and the wanted ones are watched
+fn is_wanted_led(name: &str, extra: &[String]) -> bool {
+    name.ends_with("kbd_backlight") || extra.iter().any(|e| e == name)
+}
+
+/// Get all the backlights and keyboard backlights below `root`
+pub fn get_devices(root: &Path, extra_leds: &[String]) -> Result<Vec<(PathBuf, BrightnessDevice)>> {
+    let mut devices = Vec::new();
+
+    for subsystem in [BrightnessSubsystem::Backlight, BrightnessSubsystem::Leds] {
+        let Ok(entries) = fs::read_dir(root.join(subsystem.as_str())) else {
+            continue;
+        };
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            let name = path.file_name().and_then(OsStr::to_str).unwrap_or("");
+            if subsystem == BrightnessSubsystem::Leds && !is_wanted_led(name, extra_leds) {
+                continue;
+            }
+            if let Ok(device) = read_device_dir(&path, subsystem) {
+                devices.push((path, device));
+            }
+        }
+    }
+
+    Ok(devices)
+}
+
+/// Brightness Manager Struct
+pub struct BrightnessManager {
+    devices: Arc<RwLock<Vec<BrightnessDevice>>>,
+}
+
+impl BrightnessManager {
+    /// Create a new BrightnessManager watching the devices below `root`
+    pub fn new(
+        root: impl AsRef<Path>,
+        extra_leds: &[String],
+        on_change: impl Fn(&BrightnessDevice) + Send + Sync + 'static,
+    ) -> Result<Self> {
+        let inotify = Inotify::init(InitFlags::IN_CLOEXEC)?;
+        let mut watches = HashMap::<WatchDescriptor, (PathBuf, usize)>::new();
+        let mut devices = Vec::new();
+
+        for (index, (dir, device)) in get_devices(root.as_ref(), extra_leds)?
+            .into_iter()
+            .enumerate()
+        {
+            for file in WATCHED_FILES {
+                let path = dir.join(file);
+                if !path.exists() {
+                    continue;
+                }
+                let wd = inotify.add_watch(&path, AddWatchFlags::IN_MODIFY)?;
+                watches.insert(wd, (dir.clone(), index));
+            }
+            devices.push(device);
+        }
+
+        let devices = Arc::new(RwLock::new(devices));
+
+        {
+            let devices = devices.clone();
+            std::thread::spawn(move || {
+                while let Ok(events) = inotify.read_events() {
+                    for event in events {
+                        let Some((dir, index)) = watches.get(&event.wd) else {
+                            continue;
+                        };
+                        let mut devices = devices.write();
+                        let Ok(device) = read_device_dir(dir, devices[*index].subsystem) else {
+                            continue;
+                        };
+                        // Several files changes with the same write
+                        if device.brightness == devices[*index].brightness {
+                            continue;
+                        }
+                        devices[*index] = device;
+                        on_change(&devices[*index]);
+                    }
+                }
+            });
+        }
+
+        Ok(Self { devices })
+    }
+
+    pub fn all(&self) -> Vec<BrightnessDevice> {
+        self.devices.read().clone()
+    }
+}
+
+/// Write the brightness through logind, so root permissions are not needed
+///
+/// `value` is a percentage from 0 to 100 of the device max brightness
+pub async fn set_brightness(
+    subsystem: BrightnessSubsystem,
+    device: Option<&str>,
+    value: impl FnOnce(u8) -> u8,
+) -> Result<()> {
+    let root = Path::new(SYSFS_CLASS_PATH);
+    let device = get_devices(
+        root,
+        &device.map(|d| vec![d.to_owned()]).unwrap_or_default(),
+    )?
+    .into_iter()
+    .map(|(_, d)| d)
+    .find(|d| d.subsystem == subsystem && device.map_or(true, |name| *d.name == *name))
+    .ok_or(crate::Error::MissingBrightnessDevice)?;
+
+    let brightness = device.brightness_for(value(device.percent()));
+
+    Connection::system()
+        .await?
+        .call_method(
+            Some("org.freedesktop.login1"),
+            "/org/freedesktop/login1/session/auto",
+            Some("org.freedesktop.login1.Session"),
+            "SetBrightness",
+            &(subsystem.as_str(), device.name(), brightness),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn fake_sysfs(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("sosd-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&root);
+        for (dir, brightness, max) in [
+            ("backlight/intel_backlight", 9600, 19200),
+            ("leds/tpacpi::kbd_backlight", 1, 2),
+            ("leds/input3::capslock", 0, 1),
+        ] {
+            let dir = root.join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("brightness"), format!("{brightness}\n")).unwrap();
+            fs::write(dir.join("max_brightness"), format!("{max}\n")).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_get_devices() {
+        let root = fake_sysfs("brightness-devices");
+        let mut devices = get_devices(&root, &[]).unwrap();
+        devices.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].1.name(), "intel_backlight");
+        assert_eq!(devices[0].1.percent(), 50);
+        assert_eq!(devices[1].1.subsystem(), BrightnessSubsystem::Leds);
+        assert_eq!(devices[1].1.percent(), 50);
+
+        let with_extra = get_devices(&root, &["input3::capslock".to_owned()]).unwrap();
+        assert_eq!(with_extra.len(), 3);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_brightness_for() {
+        let device = |brightness, max_brightness| BrightnessDevice {
+            name: "tpacpi::kbd_backlight".into(),
+            subsystem: BrightnessSubsystem::Leds,
+            brightness,
+            max_brightness,
+        };
+
+        assert_eq!(device(9600, 19200).brightness_for(75), 14400);
+        assert_eq!(device(9600, 19200).brightness_for(50), 9600);
+        assert_eq!(device(9600, 19200).brightness_for(120), 19200);
+
+        // 5% is less than a step of a keyboard backlight with 3 levels
+        assert_eq!(device(1, 2).brightness_for(55), 2);
+        assert_eq!(device(1, 2).brightness_for(45), 0);
+        assert_eq!(device(2, 2).brightness_for(100), 2);
+        assert_eq!(device(0, 2).brightness_for(0), 0);
+    }
+
+    #[test]
+    fn test_watch_changes() {
+        let root = fake_sysfs("brightness-watch");
+        let (tx, rx) = channel();
+        let manager = BrightnessManager::new(&root, &[], move |device| {
+            _ = tx.send((device.name().to_owned(), device.percent()));
+        })
+        .unwrap();
+        assert_eq!(manager.all().len(), 2);
+
+        fs::write(root.join("backlight/intel_backlight/brightness"), "4800\n").unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(2)).unwrap(),
+            ("intel_backlight".to_owned(), 25)
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -10,10 +10,13 @@ pub enum Error {
     ZbusFdo(zbus::fdo::Error),
     Icon(IconError),
     Serialization(bincode::Error),
+    X11(x11rb::errors::ReplyError),
     ParseError(ParseIntError),
     InvalidBatteryState(String),
     MissingBatteryField(String),
     MissingCharger,
+    MissingBrightnessDevice,
+    MissingKeyboardLayout,
 
     // Singletone
     ServerNotRunning,
@@ -45,7 +48,14 @@ impl fmt::Display for Error {
             Error::MissingCharger => {
                 write!(f, "Missing Charger")
             }
+            Error::MissingBrightnessDevice => {
+                write!(f, "Missing Brightness Device")
+            }
+            Error::MissingKeyboardLayout => {
+                write!(f, "Missing Keyboard Layout Source")
+            }
             Error::Serialization(e) => write!(f, "Error with bincode: {e}"),
+            Error::X11(e) => write!(f, "X11 Error: {e}"),
             Error::Icon(e) => write!(f, "Error to handle Icon: {e}"),
 
             // Singletone
@@ -76,6 +86,12 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<nix::errno::Errno> for Error {
+    fn from(err: nix::errno::Errno) -> Self {
+        Self::IoError(err.into())
+    }
+}
+
 impl From<zbus::Error> for Error {
     fn from(err: zbus::Error) -> Self {
         Self::Zbus(err)
@@ -100,6 +116,18 @@ impl From<bincode::Error> for Error {
     }
 }
 
+impl From<x11rb::errors::ReplyError> for Error {
+    fn from(err: x11rb::errors::ReplyError) -> Self {
+        Self::X11(err)
+    }
+}
+
+impl From<x11rb::errors::ConnectionError> for Error {
+    fn from(err: x11rb::errors::ConnectionError) -> Self {
+        Self::X11(err.into())
+    }
+}
+
 impl From<IconError> for Error {
     fn from(err: IconError) -> Self {
         Self::Icon(err)
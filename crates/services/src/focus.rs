@@ -0,0 +1,151 @@
+use std::env::var_os;
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::ipc;
+
+/// The compositor answers at once, a longer wait would delay the OSD
+const TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Get the focused monitor from the `monitors` reply of Hyprland
+///
+/// ```text
+/// Monitor eDP-1 (ID 0):
+///     focused: yes
+/// ```
+fn parse_hyprland_monitors(reply: &str) -> Option<String> {
+    let mut name = None;
+    for line in reply.lines() {
+        if let Some(monitor) = line.strip_prefix("Monitor ") {
+            name = monitor.split_whitespace().next();
+        } else if line.trim() == "focused: yes" {
+            return name.map(str::to_owned);
+        }
+    }
+    None
+}
+
+/// Output of the `GET_OUTPUTS` reply of sway, the disabled ones have no `focused`
+#[derive(Deserialize)]
+struct SwayOutput {
+    name: String,
+    #[serde(default)]
+    focused: bool,
+}
+
+/// Get the focused output from the `GET_OUTPUTS` reply of sway
+fn parse_sway_outputs(reply: &str) -> Option<String> {
+    serde_json::from_str::<Vec<SwayOutput>>(reply)
+        .ok()?
+        .into_iter()
+        .find_map(|output| output.focused.then_some(output.name))
+}
+
+/// Reply of niri, like `{"Ok":{"FocusedOutput":{"name":"DP-2",...}}}`, an error has no `Ok`
+#[derive(Deserialize)]
+struct NiriReply {
+    #[serde(rename = "Ok")]
+    ok: NiriFocusedOutput,
+}
+
+#[derive(Deserialize)]
+struct NiriFocusedOutput {
+    #[serde(rename = "FocusedOutput")]
+    output: Option<NiriOutput>,
+}
+
+#[derive(Deserialize)]
+struct NiriOutput {
+    name: String,
+}
+
+/// Get the output from the `FocusedOutput` reply of niri
+fn parse_niri_output(reply: &str) -> Option<String> {
+    let reply = serde_json::from_str::<NiriReply>(reply).ok()?;
+    reply.ok.output.map(|output| output.name)
+}
+
+fn connect(socket: impl Into<PathBuf>) -> Option<UnixStream> {
+    let stream = UnixStream::connect(socket.into()).ok()?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(TIMEOUT)).ok()?;
+    Some(stream)
+}
+
+fn hyprland_output(signature: &OsStr) -> Option<String> {
+    let runtime = var_os("XDG_RUNTIME_DIR").map(|d| PathBuf::from(d).join("hypr"));
+    let mut stream = [runtime, Some(PathBuf::from("/tmp/hypr"))]
+        .into_iter()
+        .flatten()
+        .find_map(|dir| connect(dir.join(signature).join(".socket.sock")))?;
+    stream.write_all(b"monitors").ok()?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).ok()?;
+    parse_hyprland_monitors(&reply)
+}
+
+fn sway_output(socket: &OsStr) -> Option<String> {
+    let mut stream = connect(socket)?;
+    ipc::sway_request(&mut stream, ipc::GET_OUTPUTS, &[]).ok()?;
+    parse_sway_outputs(&String::from_utf8_lossy(&ipc::sway_read(&mut stream).ok()?))
+}
+
+fn niri_output(socket: &OsStr) -> Option<String> {
+    let mut stream = connect(socket)?;
+    stream.write_all(b"\"FocusedOutput\"\n").ok()?;
+
+    // The reply is a line, the socket stays open for more requests
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).ok()?;
+    parse_niri_output(&reply)
+}
+
+/// Name of the output with the keyboard focus, asked to the compositor through its ipc
+///
+/// Only sway, Hyprland and niri are supported
+pub fn focused_output() -> Option<String> {
+    if let Some(socket) = var_os("SWAYSOCK") {
+        return sway_output(&socket);
+    }
+    if let Some(signature) = var_os("HYPRLAND_INSTANCE_SIGNATURE") {
+        return hyprland_output(&signature);
+    }
+    if let Some(socket) = var_os("NIRI_SOCKET") {
+        return niri_output(&socket);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_focused_output() {
+        let hyprland = "Monitor eDP-1 (ID 0):\n\t2880x1800@90.00000 at 0x0\n\tfocused: no\n\n\
+                        Monitor DP-3 (ID 1):\n\t2560x1440@144.00000 at 2880x0\n\tfocused: yes\n";
+        assert_eq!(parse_hyprland_monitors(hyprland).as_deref(), Some("DP-3"));
+        assert_eq!(parse_hyprland_monitors("Monitor eDP-1 (ID 0):\n"), None);
+
+        // From `swaymsg -t get_outputs -r`, with a disabled output
+        let sway = include_str!("../tests/fixtures/sway_outputs.json");
+        assert_eq!(parse_sway_outputs(sway).as_deref(), Some("DP-2"));
+        assert_eq!(parse_sway_outputs("[]"), None);
+
+        let niri = r#"{"Ok":{"FocusedOutput":{"name":"DP-\"2\"","make":"Dell","model":"U2720Q"}}}"#;
+        assert_eq!(parse_niri_output(niri).as_deref(), Some(r#"DP-"2""#));
+        assert_eq!(parse_niri_output(r#"{"Ok":{"FocusedOutput":null}}"#), None);
+        assert_eq!(
+            parse_niri_output(r#"{ "Ok": { "FocusedOutput": { "name": "DP-\u0032" } } }"#)
+                .as_deref(),
+            Some("DP-2")
+        );
+        assert_eq!(parse_niri_output(r#"{"Err":"no such request"}"#), None);
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use std::fs;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+
+use nix::sys::socket::{
+    bind, recv, socket, AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol, SockType,
+};
+use parking_lot::Mutex;
+
+use crate::Result;
+
+pub const SYSFS_PATH: &str = "/sys";
+
+/// Multicast group of the kernel events, udev sends the processed ones to the second
+const KERNEL_GROUP: u32 = 1;
+
+/// Hotplug Action Enum
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum HotplugAction {
+    Added,
+    Removed,
+    /// Only sent by `drm`, when a display is connected or disconnected
+    Changed,
+}
+
+/// A device with a name to show
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct HotplugDevice {
+    subsystem: String,
+    name: String,
+    /// Vendor and product ids, like `1050:0407`
+    id: Option<String>,
+    devpath: String,
+}
+
+impl HotplugDevice {
+    /// Kernel subsystem, like `usb` or `sound`
+    pub fn subsystem(&self) -> &str {
+        &self.subsystem
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Match the id, or a part of the name ignoring the case
+    pub fn matches(&self, filter: &str) -> bool {
+        self.id.as_deref() == Some(filter)
+            || self.name.to_lowercase().contains(&filter.to_lowercase())
+    }
+
+    /// If the device is below `parent` in the device tree, like the input of a usb keyboard
+    fn is_child_of(&self, parent: &str) -> bool {
+        self.devpath
+            .strip_prefix(parent)
+            .is_some_and(|rest| rest.starts_with('/'))
+    }
+}
+
+/// Hotplug Event Struct
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct HotplugEvent {
+    pub action: HotplugAction,
+    pub device: HotplugDevice,
+}
+
+/// A kernel uevent, like `add@/devices/...\0ACTION=add\0SUBSYSTEM=usb\0...`
+#[derive(Debug)]
+struct Uevent<'a> {
+    properties: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Uevent<'a> {
+    fn parse(payload: &'a [u8]) -> Option<Self> {
+        // The events resent by udev start with `libudev`, the header is `action@devpath`
+        let mut fields = payload.split(|b| *b == 0);
+        if !fields.next()?.contains(&b'@') {
+            return None;
+        }
+        let properties = fields
+            .filter_map(|field| std::str::from_utf8(field).ok()?.split_once('='))
+            .collect();
+        Some(Self { properties })
+    }
+
+    fn get(&self, key: &str) -> Option<&'a str> {
+        self.properties.get(key).copied()
+    }
+}
+
+/// Read a sysfs attribute of the device
+fn read_attribute(root: &Path, devpath: &str, attribute: &str) -> Option<String> {
+    let path = root.join(devpath.trim_start_matches('/')).join(attribute);
+    let value = fs::read_to_string(path).ok()?;
+    Some(value.trim().to_owned()).filter(|v| !v.is_empty())
+}
+
+/// Vendor and product ids from `PRODUCT`, like `1050/407/543` for usb or `3/1050/407/110` for input
+fn parse_product_id(product: &str, skip: usize) -> Option<String> {
+    let mut ids = product.split('/').skip(skip);
+    let vendor = u16::from_str_radix(ids.next()?, 16).ok()?;
+    let product = u16::from_str_radix(ids.next()?, 16).ok()?;
+    Some(format!("{vendor:04x}:{product:04x}"))
+}
+
+/// Hotplug Manager Struct
+pub struct HotplugManager {
+    root: PathBuf,
+    subsystems: Vec<String>,
+    allow: Vec<String>,
+    deny: Vec<String>,
+    /// Added devices by devpath, the removed ones no longer have sysfs attributes to read the name
+    devices: Mutex<HashMap<String, HotplugDevice>>,
+}
+
+impl HotplugManager {
+    /// Create a new HotplugManager, `root` is the sysfs path
+    ///
+    /// A device is shown when it matches any `allow` filter, or there are none, and no `deny` one
+    pub fn new(
+        root: impl Into<PathBuf>,
+        subsystems: Vec<String>,
+        allow: Vec<String>,
+        deny: Vec<String>,
+    ) -> Self {
+        Self {
+            root: root.into(),
+            subsystems,
+            allow,
+            deny,
+            devices: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Read the name of the device from the uevent and its sysfs attributes
+    fn read_device(&self, event: &Uevent, devpath: &str, subsystem: &str) -> Option<HotplugDevice> {
+        let attribute = |name| read_attribute(&self.root, devpath, name);
+        let (name, id) = match subsystem {
+            // The interfaces of the device send their own events
+            "usb" if event.get("DEVTYPE") == Some("usb_device") => {
+                let id = event.get("PRODUCT").and_then(|p| parse_product_id(p, 0));
+                let name = match (attribute("manufacturer"), attribute("product")) {
+                    (Some(vendor), Some(product)) if !product.starts_with(&vendor) => {
+                        Some(format!("{vendor} {product}"))
+                    }
+                    (_, Some(product)) => Some(product),
+                    (vendor, None) => vendor,
+                };
+                (name, id)
+            }
+            // The event nodes below the device have no name
+            "input" => (
+                Some(event.get("NAME")?.trim_matches('"').to_owned()),
+                event.get("PRODUCT").and_then(|p| parse_product_id(p, 1)),
+            ),
+            // The control and pcm nodes below the card have no id
+            "sound" if devpath.rsplit('/').next()?.starts_with("card") => {
+                (Some(attribute("id")?), None)
+            }
+            "drm" if event.get("HOTPLUG") == Some("1") => (Some("Display".to_owned()), None),
+            _ => return None,
+        };
+
+        Some(HotplugDevice {
+            subsystem: subsystem.to_owned(),
+            name: name
+                .or_else(|| id.as_ref().map(|id| format!("Device {id}")))
+                .unwrap_or_else(|| subsystem.to_owned()),
+            id,
+            devpath: devpath.to_owned(),
+        })
+    }
+
+    fn is_allowed(&self, device: &HotplugDevice) -> bool {
+        (self.allow.is_empty() || self.allow.iter().any(|f| device.matches(f)))
+            && !self.deny.iter().any(|f| device.matches(f))
+    }
+
+    /// Handle a uevent payload, returning the event to show if any
+    pub fn handle(&self, payload: &[u8]) -> Option<HotplugEvent> {
+        let event = Uevent::parse(payload)?;
+        let subsystem = event.get("SUBSYSTEM")?;
+        if !self.subsystems.iter().any(|s| s == subsystem) {
+            return None;
+        }
+        let devpath = event.get("DEVPATH")?;
+        let mut devices = self.devices.lock();
+
+        let (action, device) = match event.get("ACTION")? {
+            "add" => {
+                let device = self.read_device(&event, devpath, subsystem)?;
+                // A headset is an usb device with a sound card and an input, show it once
+                if devices.keys().any(|parent| device.is_child_of(parent)) {
+                    return None;
+                }
+                devices.insert(devpath.to_owned(), device.clone());
+                (HotplugAction::Added, device)
+            }
+            "remove" => (HotplugAction::Removed, devices.remove(devpath)?),
+            "change" => (
+                HotplugAction::Changed,
+                self.read_device(&event, devpath, subsystem)?,
+            ),
+            _ => return None,
+        };
+
+        self.is_allowed(&device)
+            .then_some(HotplugEvent { action, device })
+    }
+
+    /// Block listening the kernel uevents
+    pub fn listen(&self, on_event: impl Fn(&HotplugEvent)) -> Result<()> {
+        let socket = open_uevent_socket()?;
+        let mut buf = vec![0; 8192];
+        loop {
+            let len = recv(socket.as_raw_fd(), &mut buf, MsgFlags::empty())?;
+            if let Some(event) = self.handle(&buf[..len]) {
+                on_event(&event);
+            }
+        }
+    }
+}
+
+fn open_uevent_socket() -> Result<OwnedFd> {
+    let socket = socket(
+        AddressFamily::Netlink,
+        SockType::Datagram,
+        SockFlag::SOCK_CLOEXEC,
+        SockProtocol::NetlinkKObjectUEvent,
+    )?;
+    // The kernel assigns the port when it is zero
+    bind(socket.as_raw_fd(), &NetlinkAddr::new(0, KERNEL_GROUP))?;
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USB: &str = "/devices/pci0000:00/0000:00:14.0/usb1/1-2";
+    const HEADSET: &str = "/devices/pci0000:00/0000:00:14.0/usb1/1-3/1-3:1.0/sound/card2";
+
+    /// Payloads recorded with a yubikey, a bluetooth mouse, an usb headset and a monitor
+    const YUBIKEY_ADD: &[u8] = b"add@/devices/pci0000:00/0000:00:14.0/usb1/1-2\0ACTION=add\0\
+        DEVPATH=/devices/pci0000:00/0000:00:14.0/usb1/1-2\0SUBSYSTEM=usb\0MAJOR=189\0MINOR=5\0\
+        DEVNAME=bus/usb/001/006\0DEVTYPE=usb_device\0PRODUCT=1050/407/543\0TYPE=0/0/0\0\
+        BUSNUM=001\0DEVNUM=006\0SEQNUM=4512\0";
+    const YUBIKEY_INTERFACE_ADD: &[u8] =
+        b"add@/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.0\0ACTION=add\0\
+        DEVPATH=/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.0\0SUBSYSTEM=usb\0\
+        DEVTYPE=usb_interface\0PRODUCT=1050/407/543\0TYPE=0/0/0\0INTERFACE=3/1/1\0SEQNUM=4513\0";
+    const YUBIKEY_INPUT_ADD: &[u8] =
+        b"add@/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.0/0003:1050:0407.0009/input/input27\0\
+        ACTION=add\0\
+        DEVPATH=/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.0/0003:1050:0407.0009/input/input27\0\
+        SUBSYSTEM=input\0PRODUCT=3/1050/407/110\0NAME=\"Yubico YubiKey OTP+FIDO+CCID\"\0\
+        PHYS=\"usb-0000:00:14.0-2/input0\"\0PROP=0\0EV=120013\0SEQNUM=4515\0";
+    const YUBIKEY_REMOVE: &[u8] = b"remove@/devices/pci0000:00/0000:00:14.0/usb1/1-2\0\
+        ACTION=remove\0DEVPATH=/devices/pci0000:00/0000:00:14.0/usb1/1-2\0SUBSYSTEM=usb\0\
+        MAJOR=189\0MINOR=5\0DEVNAME=bus/usb/001/006\0DEVTYPE=usb_device\0PRODUCT=1050/407/543\0\
+        TYPE=0/0/0\0BUSNUM=001\0DEVNUM=006\0SEQNUM=4530\0";
+    const MOUSE_ADD: &[u8] =
+        b"add@/devices/virtual/misc/uhid/0005:046D:B023.000A/input/input30\0ACTION=add\0\
+        DEVPATH=/devices/virtual/misc/uhid/0005:046D:B023.000A/input/input30\0SUBSYSTEM=input\0\
+        PRODUCT=5/46d/b023/11\0NAME=\"MX Master 3 Mouse\"\0PROP=0\0EV=17\0SEQNUM=4601\0";
+    const MOUSE_EVENT_ADD: &[u8] =
+        b"add@/devices/virtual/misc/uhid/0005:046D:B023.000A/input/input30/event12\0ACTION=add\0\
+        DEVPATH=/devices/virtual/misc/uhid/0005:046D:B023.000A/input/input30/event12\0\
+        SUBSYSTEM=input\0MAJOR=13\0MINOR=76\0DEVNAME=input/event12\0SEQNUM=4602\0";
+    const HEADSET_ADD: &[u8] =
+        b"add@/devices/pci0000:00/0000:00:14.0/usb1/1-3/1-3:1.0/sound/card2\0ACTION=add\0\
+        DEVPATH=/devices/pci0000:00/0000:00:14.0/usb1/1-3/1-3:1.0/sound/card2\0\
+        SUBSYSTEM=sound\0SEQNUM=4701\0";
+    const MONITOR_CHANGE: &[u8] = b"change@/devices/pci0000:00/0000:00:02.0/drm/card1\0\
+        ACTION=change\0DEVPATH=/devices/pci0000:00/0000:00:02.0/drm/card1\0SUBSYSTEM=drm\0\
+        HOTPLUG=1\0CONNECTOR=107\0DEVNAME=dri/card1\0DEVTYPE=drm_minor\0SEQNUM=4801\0";
+    const UDEV_ADD: &[u8] = b"libudev\0\xfe\xed\xca\xfe\0\0\0\0ACTION=add\0SUBSYSTEM=usb\0";
+
+    fn fake_sysfs(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("sosd-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&root);
+        let usb = root.join(USB.trim_start_matches('/'));
+        fs::create_dir_all(&usb).unwrap();
+        fs::write(usb.join("manufacturer"), "Yubico\n").unwrap();
+        fs::write(usb.join("product"), "YubiKey OTP+FIDO+CCID\n").unwrap();
+        let headset = root.join(HEADSET.trim_start_matches('/'));
+        fs::create_dir_all(&headset).unwrap();
+        fs::write(headset.join("id"), "Headset\n").unwrap();
+        root
+    }
+
+    fn all_subsystems() -> Vec<String> {
+        ["usb", "input", "sound", "drm"].map(String::from).to_vec()
+    }
+
+    #[test]
+    fn test_usb_device_and_children() {
+        let root = fake_sysfs("hotplug-usb");
+        let manager = HotplugManager::new(&root, all_subsystems(), vec![], vec![]);
+
+        let event = manager.handle(YUBIKEY_ADD).unwrap();
+        assert_eq!(event.action, HotplugAction::Added);
+        assert_eq!(event.device.name(), "Yubico YubiKey OTP+FIDO+CCID");
+        assert_eq!(event.device.id(), Some("1050:0407"));
+        // Shown once for the whole device
+        assert_eq!(manager.handle(YUBIKEY_INTERFACE_ADD), None);
+        assert_eq!(manager.handle(YUBIKEY_INPUT_ADD), None);
+        assert_eq!(manager.handle(UDEV_ADD), None);
+
+        // The sysfs attributes are gone when the device is removed
+        fs::remove_dir_all(root.join(USB.trim_start_matches('/'))).unwrap();
+        let event = manager.handle(YUBIKEY_REMOVE).unwrap();
+        assert_eq!(event.action, HotplugAction::Removed);
+        assert_eq!(event.device.name(), "Yubico YubiKey OTP+FIDO+CCID");
+        assert_eq!(manager.handle(YUBIKEY_REMOVE), None);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_input_sound_and_drm() {
+        let root = fake_sysfs("hotplug-others");
+        let manager = HotplugManager::new(&root, all_subsystems(), vec![], vec![]);
+
+        let mouse = manager.handle(MOUSE_ADD).unwrap();
+        assert_eq!(mouse.device.name(), "MX Master 3 Mouse");
+        assert_eq!(mouse.device.id(), Some("046d:b023"));
+        assert_eq!(manager.handle(MOUSE_EVENT_ADD), None);
+
+        let headset = manager.handle(HEADSET_ADD).unwrap();
+        assert_eq!(headset.device.subsystem(), "sound");
+        assert_eq!(headset.device.name(), "Headset");
+
+        let monitor = manager.handle(MONITOR_CHANGE).unwrap();
+        assert_eq!(monitor.action, HotplugAction::Changed);
+        assert_eq!(monitor.device.name(), "Display");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_filters() {
+        let root = fake_sysfs("hotplug-filters");
+        let manager = HotplugManager::new(
+            &root,
+            vec!["usb".to_owned(), "input".to_owned()],
+            vec![],
+            vec!["yubikey".to_owned()],
+        );
+        assert_eq!(manager.handle(YUBIKEY_ADD), None);
+        // Denied devices still hide their children
+        assert_eq!(manager.handle(YUBIKEY_INPUT_ADD), None);
+        assert_eq!(manager.handle(HEADSET_ADD), None);
+
+        let manager = HotplugManager::new(
+            &root,
+            all_subsystems(),
+            vec!["046d:b023".to_owned()],
+            vec![],
+        );
+        assert_eq!(manager.handle(YUBIKEY_ADD), None);
+        assert!(manager.handle(MOUSE_ADD).is_some());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use parking_lot::Mutex;
+
+use crate::Result;
+
+/// A temperature sensor of a hwmon chip
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensor {
+    chip: String,
+    label: String,
+    /// Degrees Celsius
+    temperature: f32,
+    /// Fastest fan of the same chip
+    fan_rpm: Option<u32>,
+}
+
+impl Sensor {
+    /// Name of the chip, like `coretemp` or `amdgpu`
+    pub fn chip(&self) -> &str {
+        &self.chip
+    }
+
+    /// The label of the sensor, like `Package id 0`, or its file name without one
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn temperature(&self) -> f32 {
+        self.temperature
+    }
+
+    pub fn fan_rpm(&self) -> Option<u32> {
+        self.fan_rpm
+    }
+
+    /// Unique between chips, like `coretemp/Package id 0`
+    pub fn id(&self) -> String {
+        format!("{}/{}", self.chip, self.label)
+    }
+
+    /// Match the label, the chip or both like `k10temp/Tctl`
+    pub fn matches(&self, filter: &str) -> bool {
+        filter == self.label || filter == self.chip || filter == self.id()
+    }
+}
+
+fn read_trimmed(path: impl AsRef<Path>) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_owned())
+}
+
+fn read_chip(path: &Path) -> Vec<Sensor> {
+    let chip = read_trimmed(path.join("name")).unwrap_or_else(|| {
+        path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    });
+    let Ok(entries) = fs::read_dir(path) else {
+        return Vec::new();
+    };
+    let files: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().into_string().ok())
+        .collect();
+
+    let fan_rpm = files
+        .iter()
+        .filter(|f| f.starts_with("fan") && f.ends_with("_input"))
+        .filter_map(|f| read_trimmed(path.join(f))?.parse::<u32>().ok())
+        .max();
+
+    let mut sensors: Vec<Sensor> = files
+        .iter()
+        .filter_map(|f| f.strip_prefix("temp")?.strip_suffix("_input"))
+        .filter_map(|index| {
+            let millidegrees = read_trimmed(path.join(format!("temp{index}_input")))?
+                .parse::<i32>()
+                .ok()?;
+            Some(Sensor {
+                chip: chip.clone(),
+                label: read_trimmed(path.join(format!("temp{index}_label")))
+                    .unwrap_or_else(|| format!("temp{index}")),
+                temperature: millidegrees as f32 / 1000.0,
+                fan_rpm,
+            })
+        })
+        .collect();
+    sensors.sort_by(|a, b| a.label.cmp(&b.label));
+    sensors
+}
+
+/// Read the temperature sensors of every hwmon chip, `root` is the sysfs class path
+pub fn get_sensors(root: &Path) -> Result<Vec<Sensor>> {
+    let mut chips: Vec<PathBuf> = fs::read_dir(root.join("hwmon"))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    chips.sort();
+
+    Ok(chips.iter().flat_map(|chip| read_chip(chip)).collect())
+}
+
+/// Threshold crossings of each sensor
+#[derive(Debug, Default)]
+struct ThermalAlerts {
+    /// Thresholds sorted from the lowest
+    levels: Vec<u8>,
+    hysteresis: f32,
+    /// Highest threshold reached by each sensor
+    reached: HashMap<String, u8>,
+}
+
+impl ThermalAlerts {
+    fn new(mut levels: Vec<u8>, hysteresis: f32) -> Self {
+        levels.sort_unstable();
+        Self {
+            levels,
+            hysteresis,
+            reached: HashMap::new(),
+        }
+    }
+
+    /// Return the threshold when the sensor goes above a higher one
+    ///
+    /// A reached threshold is only forgotten once the sensor is below it by the hysteresis
+    fn update(&mut self, sensor: &Sensor) -> Option<u8> {
+        let temperature = sensor.temperature;
+        let id = sensor.id();
+        let current = self
+            .levels
+            .iter()
+            .rev()
+            .find(|level| temperature >= **level as f32)
+            .copied();
+        let reached = self.reached.get(&id).copied();
+
+        match (current, reached) {
+            (Some(current), Some(reached)) if current <= reached => {
+                if temperature < reached as f32 - self.hysteresis {
+                    self.reached.insert(id, current);
+                }
+                None
+            }
+            (Some(current), _) => {
+                self.reached.insert(id, current);
+                Some(current)
+            }
+            (None, Some(reached)) => {
+                if temperature < reached as f32 - self.hysteresis {
+                    self.reached.remove(&id);
+                }
+                None
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+/// Hwmon Manager Struct
+pub struct HwmonManager {
+    root: PathBuf,
+    /// Watched sensors, all of them when empty
+    sensors: Vec<String>,
+    alerts: Mutex<ThermalAlerts>,
+}
+
+impl HwmonManager {
+    /// Create a new HwmonManager, `root` is the sysfs class path
+    pub fn new(
+        root: impl Into<PathBuf>,
+        sensors: Vec<String>,
+        levels: Vec<u8>,
+        hysteresis: f32,
+    ) -> Self {
+        Self {
+            root: root.into(),
+            sensors,
+            alerts: Mutex::new(ThermalAlerts::new(levels, hysteresis)),
+        }
+    }
+
+    /// Read the sensors, returning the ones that crossed a threshold with it
+    pub fn refresh(&self) -> Result<Vec<(u8, Sensor)>> {
+        let mut alerts = self.alerts.lock();
+        Ok(get_sensors(&self.root)?
+            .into_iter()
+            .filter(|s| self.sensors.is_empty() || self.sensors.iter().any(|f| s.matches(f)))
+            .filter_map(|sensor| Some((alerts.update(&sensor)?, sensor)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_hwmon(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("sosd-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&root);
+        let coretemp = root.join("hwmon/hwmon0");
+        fs::create_dir_all(&coretemp).unwrap();
+        fs::write(coretemp.join("name"), "coretemp\n").unwrap();
+        fs::write(coretemp.join("temp1_input"), "45000\n").unwrap();
+        fs::write(coretemp.join("temp1_label"), "Package id 0\n").unwrap();
+        fs::write(coretemp.join("temp2_input"), "41000\n").unwrap();
+        fs::write(coretemp.join("temp2_label"), "Core 0\n").unwrap();
+
+        let gpu = root.join("hwmon/hwmon1");
+        fs::create_dir_all(&gpu).unwrap();
+        fs::write(gpu.join("name"), "amdgpu\n").unwrap();
+        fs::write(gpu.join("temp1_input"), "52500\n").unwrap();
+        fs::write(gpu.join("fan1_input"), "1800\n").unwrap();
+        root
+    }
+
+    fn set_temperature(root: &Path, file: &str, degrees: f32) {
+        fs::write(root.join(file), format!("{}\n", (degrees * 1000.0) as i32)).unwrap();
+    }
+
+    #[test]
+    fn test_get_sensors() {
+        let root = fake_hwmon("hwmon-sensors");
+        let sensors = get_sensors(&root).unwrap();
+
+        let ids: Vec<String> = sensors.iter().map(Sensor::id).collect();
+        assert_eq!(
+            ids,
+            ["coretemp/Core 0", "coretemp/Package id 0", "amdgpu/temp1"]
+        );
+        assert_eq!(sensors[1].temperature(), 45.0);
+        assert_eq!(sensors[1].fan_rpm(), None);
+        assert_eq!(sensors[2].temperature(), 52.5);
+        assert_eq!(sensors[2].fan_rpm(), Some(1800));
+        assert!(sensors[2].matches("amdgpu"));
+        assert!(sensors[1].matches("Package id 0"));
+        assert!(!sensors[0].matches("Package id 0"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_thresholds_with_hysteresis() {
+        let root = fake_hwmon("hwmon-alerts");
+        let manager = HwmonManager::new(&root, vec!["Package id 0".to_owned()], vec![90, 80], 5.0);
+        let package = |m: &HwmonManager| -> Vec<u8> {
+            m.refresh().unwrap().into_iter().map(|(l, _)| l).collect()
+        };
+        assert!(package(&manager).is_empty());
+
+        set_temperature(&root, "hwmon/hwmon0/temp1_input", 81.0);
+        assert_eq!(package(&manager), [80]);
+        // Fluctuating around the threshold does not alert again
+        set_temperature(&root, "hwmon/hwmon0/temp1_input", 79.0);
+        assert!(package(&manager).is_empty());
+        set_temperature(&root, "hwmon/hwmon0/temp1_input", 80.5);
+        assert!(package(&manager).is_empty());
+
+        set_temperature(&root, "hwmon/hwmon0/temp1_input", 95.0);
+        assert_eq!(package(&manager), [90]);
+        set_temperature(&root, "hwmon/hwmon0/temp1_input", 86.0);
+        assert!(package(&manager).is_empty());
+        set_temperature(&root, "hwmon/hwmon0/temp1_input", 91.0);
+        assert!(package(&manager).is_empty());
+
+        // Cooling down by the hysteresis rearms the thresholds
+        set_temperature(&root, "hwmon/hwmon0/temp1_input", 84.0);
+        assert!(package(&manager).is_empty());
+        set_temperature(&root, "hwmon/hwmon0/temp1_input", 90.0);
+        assert_eq!(package(&manager), [90]);
+        set_temperature(&root, "hwmon/hwmon0/temp1_input", 60.0);
+        assert!(package(&manager).is_empty());
+        set_temperature(&root, "hwmon/hwmon0/temp1_input", 82.0);
+        assert_eq!(package(&manager), [80]);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,50 @@
+//! Framing of the i3 ipc protocol spoken by sway
+//!
+//! Every message is the magic string, the payload length, the message type and a json payload,
+//! the numbers in the native byte order
+
+use std::io::{Read, Result, Write};
+
+const MAGIC: &[u8] = b"i3-ipc";
+const HEADER_LEN: usize = MAGIC.len() + 8;
+
+pub const SUBSCRIBE: u32 = 2;
+pub const GET_OUTPUTS: u32 = 3;
+
+/// Send a message of type `kind` with its json payload
+pub fn sway_request(stream: &mut impl Write, kind: u32, payload: &[u8]) -> Result<()> {
+    let mut message = Vec::with_capacity(HEADER_LEN + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload);
+    stream.write_all(&message)
+}
+
+/// Read the payload of the next reply or event
+pub fn sway_read(stream: &mut impl Read) -> Result<Vec<u8>> {
+    let mut header = [0; HEADER_LEN];
+    stream.read_exact(&mut header)?;
+    let len = u32::from_ne_bytes(header[MAGIC.len()..MAGIC.len() + 4].try_into().unwrap());
+    let mut payload = vec![0; len as usize];
+    stream.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sway_framing() {
+        let mut message = Vec::new();
+        sway_request(&mut message, GET_OUTPUTS, b"[]").unwrap();
+        assert_eq!(&message[..6], MAGIC);
+        assert_eq!(message[6..10], 2u32.to_ne_bytes());
+        assert_eq!(message[10..14], GET_OUTPUTS.to_ne_bytes());
+
+        let mut stream = message.as_slice();
+        assert_eq!(sway_read(&mut stream).unwrap(), b"[]");
+        assert!(sway_read(&mut stream).is_err());
+    }
+}
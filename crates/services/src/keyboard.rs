@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::env::var_os;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+use parking_lot::RwLock;
+use serde::Deserialize;
+use x11rb::connection::Connection;
+use x11rb::protocol::xkb::{self, ConnectionExt as _, DeviceSpec, Group};
+use x11rb::protocol::xproto::ConnectionExt as _;
+use x11rb::protocol::Event;
+
+use crate::{ipc, Result};
+
+/// Lock Key Enum
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+pub enum LockKey {
+    CapsLock,
+    NumLock,
+    ScrollLock,
+}
+
+impl LockKey {
+    const ALL: [LockKey; 3] = [LockKey::CapsLock, LockKey::NumLock, LockKey::ScrollLock];
+
+    /// Suffix of the led name, like `input3::capslock`
+    fn led_suffix(&self) -> &'static str {
+        match self {
+            LockKey::CapsLock => "::capslock",
+            LockKey::NumLock => "::numlock",
+            LockKey::ScrollLock => "::scrolllock",
+        }
+    }
+}
+
+/// Read the lock leds below `root`, a lock is active when any keyboard has its led on
+///
+/// Without the `leds` class, like in a container, there are no locks
+pub fn get_locks(root: &Path) -> Result<BTreeMap<LockKey, bool>> {
+    let mut locks = BTreeMap::new();
+
+    let entries = match fs::read_dir(root.join("leds")) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(locks),
+        Err(e) => return Err(e.into()),
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let name = path.file_name().and_then(OsStr::to_str).unwrap_or("");
+        let Some(key) = LockKey::ALL.iter().find(|k| name.ends_with(k.led_suffix())) else {
+            continue;
+        };
+        let Ok(brightness) = fs::read_to_string(path.join("brightness")) else {
+            continue;
+        };
+        let active = brightness.trim().parse::<u32>().unwrap_or_default() > 0;
+        *locks.entry(*key).or_default() |= active;
+    }
+
+    Ok(locks)
+}
+
+/// Keyboard Manager Struct
+pub struct KeyboardManager {
+    root: PathBuf,
+    locks: RwLock<BTreeMap<LockKey, bool>>,
+}
+
+impl KeyboardManager {
+    /// Create a new KeyboardManager reading the leds below `root`
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        let locks = RwLock::new(get_locks(&root)?);
+        Ok(Self { root, locks })
+    }
+
+    pub fn lock(&self, key: LockKey) -> bool {
+        self.locks.read().get(&key).copied().unwrap_or_default()
+    }
+
+    /// Refresh the leds, returning the locks that changed
+    ///
+    /// The input layer does not notify the led changes, so it needs polling
+    pub fn refresh(&self) -> Result<Vec<(LockKey, bool)>> {
+        let locks = get_locks(&self.root)?;
+        let mut old = self.locks.write();
+        let changed = locks
+            .iter()
+            .filter(|(key, active)| old.get(key) != Some(active))
+            .map(|(key, active)| (*key, *active))
+            .collect();
+        *old = locks;
+        Ok(changed)
+    }
+}
+
+/// Get the layout from an `activelayout>>keyboard,layout` line of Hyprland
+fn parse_hyprland_event(line: &str) -> Option<&str> {
+    let (_keyboard, layout) = line.strip_prefix("activelayout>>")?.split_once(',')?;
+    Some(layout)
+}
+
+/// Input event of sway, the layout is only read on `xkb_layout` changes
+#[derive(Deserialize)]
+struct SwayInputEvent {
+    change: String,
+    input: SwayInput,
+}
+
+#[derive(Deserialize)]
+struct SwayInput {
+    xkb_active_layout_name: Option<String>,
+}
+
+/// Get the new layout from an input event of sway
+fn parse_sway_event(payload: &[u8]) -> Option<String> {
+    let event = serde_json::from_slice::<SwayInputEvent>(payload).ok()?;
+    if event.change != "xkb_layout" {
+        return None;
+    }
+    event.input.xkb_active_layout_name
+}
+
+fn listen_hyprland(signature: &OsStr, on_layout: &impl Fn(&str)) -> Result<()> {
+    let runtime = var_os("XDG_RUNTIME_DIR").map(|d| PathBuf::from(d).join("hypr"));
+    let stream = [runtime, Some(PathBuf::from("/tmp/hypr"))]
+        .into_iter()
+        .flatten()
+        .find_map(|dir| UnixStream::connect(dir.join(signature).join(".socket2.sock")).ok())
+        .ok_or(crate::Error::MissingKeyboardLayout)?;
+
+    for line in BufReader::new(stream).lines() {
+        if let Some(layout) = parse_hyprland_event(&line?) {
+            on_layout(layout);
+        }
+    }
+    Ok(())
+}
+
+/// Subscribed to the input events of sway
+fn listen_sway(socket: &OsStr, on_layout: &impl Fn(&str)) -> Result<()> {
+    let mut stream = UnixStream::connect(socket)?;
+    ipc::sway_request(&mut stream, ipc::SUBSCRIBE, br#"["input"]"#)?;
+
+    // The first message is the reply of the subscription, it is not an event
+    loop {
+        if let Some(layout) = parse_sway_event(&ipc::sway_read(&mut stream)?) {
+            on_layout(&layout);
+        }
+    }
+}
+
+/// Name of the XKB group, it is the layout like `English (US)`
+fn x11_layout(conn: &impl Connection, device: DeviceSpec, group: Group) -> Result<Option<String>> {
+    let names = conn
+        .xkb_get_names(device, xkb::NameDetail::GROUP_NAMES)?
+        .reply()?;
+    let Some(atom) = names
+        .value_list
+        .groups
+        .and_then(|groups| groups.get(u8::from(group) as usize).copied())
+    else {
+        return Ok(None);
+    };
+    let name = conn.get_atom_name(atom)?.reply()?.name;
+    Ok(Some(String::from_utf8_lossy(&name).into_owned()))
+}
+
+/// Follows the group of the core keyboard, and its names when the layouts are changed
+fn listen_x11(on_layout: &impl Fn(&str)) -> Result<()> {
+    let (conn, _) = x11rb::connect(None).map_err(|_| crate::Error::MissingKeyboardLayout)?;
+    if !conn.xkb_use_extension(1, 0)?.reply()?.supported {
+        return Err(crate::Error::MissingKeyboardLayout);
+    }
+    let device = xkb::ID::USE_CORE_KBD.into();
+    let details = xkb::SelectEventsAux::new().state_notify(xkb::SelectEventsAuxStateNotify {
+        affect_state: xkb::StatePart::GROUP_STATE,
+        state_details: xkb::StatePart::GROUP_STATE,
+    });
+    conn.xkb_select_events(
+        device,
+        xkb::EventType::from(0u16),
+        xkb::EventType::NAMES_NOTIFY,
+        xkb::MapPart::from(0u16),
+        xkb::MapPart::from(0u16),
+        &details,
+    )?
+    .check()?;
+
+    let mut group = conn.xkb_get_state(device)?.reply()?.group;
+    let mut layout = x11_layout(&conn, device, group)?;
+    loop {
+        match conn.wait_for_event()? {
+            Event::XkbStateNotify(event) => group = event.group,
+            Event::XkbNamesNotify(_) => {}
+            _ => continue,
+        }
+        let current = x11_layout(&conn, device, group)?;
+        if current != layout {
+            if let Some(name) = current.as_deref() {
+                on_layout(name);
+            }
+            layout = current;
+        }
+    }
+}
+
+/// Block listening the active layout, from sway, Hyprland or the XKB extension of X11
+///
+/// Other Wayland compositors do not expose the layout, so there only the locks are shown
+pub fn listen_layout(on_layout: impl Fn(&str)) -> Result<()> {
+    if let Some(socket) = var_os("SWAYSOCK") {
+        return listen_sway(&socket, &on_layout);
+    }
+    if let Some(signature) = var_os("HYPRLAND_INSTANCE_SIGNATURE") {
+        return listen_hyprland(&signature, &on_layout);
+    }
+    // Under Wayland the XKB state of Xwayland is not the one of the compositor
+    if var_os("WAYLAND_DISPLAY").is_none() && var_os("DISPLAY").is_some() {
+        return listen_x11(&on_layout);
+    }
+    Err(crate::Error::MissingKeyboardLayout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_leds(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("sosd-{name}-{}", std::process::id()));
+        _ = fs::remove_dir_all(&root);
+        for (led, brightness) in [
+            ("input3::capslock", 0),
+            ("input3::numlock", 1),
+            ("input12::capslock", 0),
+            ("tpacpi::kbd_backlight", 2),
+        ] {
+            let dir = root.join("leds").join(led);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("brightness"), format!("{brightness}\n")).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_read_locks() {
+        let root = fake_leds("keyboard-locks");
+        let manager = KeyboardManager::new(&root).unwrap();
+
+        assert!(!manager.lock(LockKey::CapsLock));
+        assert!(manager.lock(LockKey::NumLock));
+        assert!(!manager.lock(LockKey::ScrollLock));
+        fs::remove_dir_all(root).unwrap();
+
+        let empty = std::env::temp_dir().join("sosd-keyboard-no-leds");
+        assert!(KeyboardManager::new(&empty)
+            .unwrap()
+            .refresh()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_refresh_locks() {
+        let root = fake_leds("keyboard-refresh");
+        let manager = KeyboardManager::new(&root).unwrap();
+        assert!(manager.refresh().unwrap().is_empty());
+
+        // Only one of the keyboards is enough
+        fs::write(root.join("leds/input12::capslock/brightness"), "1\n").unwrap();
+        assert_eq!(manager.refresh().unwrap(), [(LockKey::CapsLock, true)]);
+        assert!(manager.lock(LockKey::CapsLock));
+        assert!(manager.refresh().unwrap().is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_parse_layout_events() {
+        assert_eq!(
+            parse_hyprland_event(
+                "activelayout>>at-translated-set-2-keyboard,Spanish (Latin American)"
+            ),
+            Some("Spanish (Latin American)")
+        );
+        assert_eq!(parse_hyprland_event("workspace>>2"), None);
+
+        let sway = r#"{ "change": "xkb_layout", "input": { "identifier": "1:1:AT_Translated_Set_2_keyboard", "xkb_active_layout_name": "English (US, \"intl\")", "xkb_active_layout_index": 0 } }"#;
+        assert_eq!(
+            parse_sway_event(sway.as_bytes()).as_deref(),
+            Some(r#"English (US, "intl")"#)
+        );
+        let compact =
+            r#"{"change":"xkb_layout","input":{"xkb_active_layout_name":"Espa\u00f1ol"}}"#;
+        assert_eq!(
+            parse_sway_event(compact.as_bytes()).as_deref(),
+            Some("Español")
+        );
+        let added =
+            r#"{ "change": "added", "input": { "xkb_active_layout_name": "English (US)" } }"#;
+        assert_eq!(parse_sway_event(added.as_bytes()), None);
+        assert_eq!(parse_sway_event(br#"{ "success": true }"#), None);
+    }
+}
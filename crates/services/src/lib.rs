@@ -1,22 +1,48 @@
+mod audio;
 mod battery;
+mod brightness;
+mod focus;
+mod hotplug;
+mod hwmon;
+mod ipc;
+mod keyboard;
+mod mpris;
+mod network;
 mod notification;
+mod power;
+mod privacy;
 mod singletone;
+mod storage;
+#[cfg(test)]
+mod test_bus;
 
 pub mod error;
 
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
 use zbus::connection::Builder;
 use zbus::Connection;
 
+pub use audio::*;
 pub use battery::*;
+pub use brightness::*;
 pub use error::Error;
-pub use notification::{Icon, Notification};
+pub use focus::focused_output;
+pub use hotplug::*;
+pub use hwmon::*;
+pub use keyboard::*;
+pub use mpris::*;
+pub use network::*;
+pub use notification::{CloseReason, Icon, Notification, Timeout};
+pub use power::*;
+pub use privacy::*;
 pub use singletone::SingletoneListener;
+pub use storage::*;
 pub use zbus;
 
 use notification::{NotificationIPC, NotificationIPCSignals};
@@ -24,12 +50,41 @@ use singletone::{SingletoneClientProxy, SingletoneServer};
 
 use self::singletone::GenericMessage;
 
+/// Time between the reads of a polled service, the default is used for a negative, zero or not
+/// finite config value
+fn refresh_interval(secs: f32, default: f32) -> Duration {
+    Duration::try_from_secs_f32(secs)
+        .ok()
+        .filter(|d| !d.is_zero())
+        .unwrap_or_else(|| {
+            eprintln!("Invalid refresh time {secs}, using {default} seconds");
+            Duration::from_secs_f32(default)
+        })
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 pub trait ServiceReceive {
     fn set_broadcast(&mut self, broadcast: ServiceBroadcast);
     fn charger_connected(&mut self, state: bool);
     fn batteries_below(&mut self, level: u8, batteries: &[Battery]);
+    fn brightness_changed(&mut self, _device: &BrightnessDevice) {}
+    fn audio_changed(&mut self, _device: &AudioDevice) {}
+    fn lock_changed(&mut self, _key: LockKey, _active: bool) {}
+    fn layout_changed(&mut self, _layout: &str) {}
+    fn now_playing(&mut self, _now_playing: &NowPlaying) {}
+    fn network_changed(&mut self, _event: &NetworkEvent) {}
+    fn power_changed(&mut self, _event: &PowerEvent) {}
+    /// Called when the sensor goes above the `level` threshold in degrees Celsius
+    fn temperature_alert(&mut self, _level: u8, _sensor: &Sensor) {}
+    /// Called when the free space goes below the `level` threshold in percent
+    fn disk_space_low(&mut self, _level: u8, _usage: &DiskUsage) {}
+    fn mount_changed(&mut self, _event: &MountEvent) {}
+    fn hotplug(&mut self, _event: &HotplugEvent) {}
+    /// Called when an application starts or stops capturing the microphone or the camera
+    fn privacy_changed(&mut self, _state: &CaptureState) {}
+    /// Called with the refreshed batteries after a resume from suspend
+    fn resumed(&mut self, _batteries: &[Battery]) {}
 }
 
 // This send to app to call actions who is hear by this crate
@@ -37,6 +92,7 @@ pub trait ServiceReceive {
 pub struct ServiceBroadcast {
     notification: Option<Connection>,
     singletone: Option<SingletoneClientProxy<'static>>,
+    mpris: Option<Arc<MprisManager>>,
 }
 
 pub struct ServiceManager<T, Message>
@@ -48,12 +104,18 @@ where
     battery: Option<BatteryManager>,
     refresh_time: Duration,
     battery_levels: Vec<u8>,
+    sleep: SleepState,
     receiver: Arc<Mutex<T>>,
     _msg: PhantomData<Message>,
 }
 
 impl ServiceBroadcast {
     pub async fn notify_action<T: Notification + 'static>(&self, id: u32, action: &str) {
+        // The now playing OSD is not a real notification, its actions go to the player
+        if let Some(mpris) = self.mpris.as_ref().filter(|m| m.id() == id) {
+            _ = mpris.play_pause().await;
+            return;
+        }
         let Some(notification) = self.notification.clone() else {
             return;
         };
@@ -67,6 +129,21 @@ impl ServiceBroadcast {
             .await
             .unwrap();
     }
+
+    pub async fn notify_closed<T: Notification + 'static>(&self, id: u32, reason: CloseReason) {
+        let Some(notification) = self.notification.clone() else {
+            return;
+        };
+
+        notification
+            .object_server()
+            .interface::<_, NotificationIPC<T>>("/org/freedesktop/Notifications")
+            .await
+            .unwrap()
+            .notification_closed(id, reason as u32)
+            .await
+            .unwrap();
+    }
 }
 
 impl<T, Message> ServiceManager<T, Message>
@@ -94,6 +171,7 @@ where
                 notification.ok()
             },
             singletone: None,
+            mpris: None,
         };
         {
             receiver.lock().set_broadcast(broadcast.clone());
@@ -105,6 +183,7 @@ where
             broadcast,
             battery: None,
             battery_levels: Vec::new(),
+            sleep: SleepState::default(),
             refresh_time: Duration::from_secs_f32(5.0),
             _msg: Default::default(),
         }
@@ -117,28 +196,245 @@ where
         levels: Vec<u8>,
     ) -> Result<Self> {
         let battery = if enable && self.is_daemon {
-            let receiver = self.receiver.clone();
-            Some(
-                BatteryManager::new(move |charger_connected| {
-                    let mut receiver = { receiver.lock() };
-                    receiver.charger_connected(charger_connected);
-                })
-                .await?,
-            )
+            Some(BatteryManager::new().await?)
         } else {
             None
         };
         Ok(Self {
             battery,
             battery_levels: levels,
-            refresh_time: Duration::from_secs_f32(refresh_time),
+            refresh_time: refresh_interval(refresh_time, 30.0),
             ..self
         })
     }
 
+    pub fn with_brightness(self, enable: bool, extra_leds: &[String]) -> Result<Self> {
+        if enable && self.is_daemon {
+            let receiver = self.receiver.clone();
+            // The watcher thread owns the manager state, so it is not needed to keep it
+            BrightnessManager::new(SYSFS_CLASS_PATH, extra_leds, move |device| {
+                receiver.lock().brightness_changed(device);
+            })?;
+        }
+        Ok(self)
+    }
+
+    pub fn with_audio(self, enable: bool) -> Self {
+        if enable && self.is_daemon {
+            let receiver = self.receiver.clone();
+            // The server can start after us or be restarted, so keep reconnecting
+            std::thread::spawn(move || loop {
+                if let Some(mut audio) = default_socket().and_then(|s| AudioManager::new(s).ok()) {
+                    _ = audio.listen(|device| receiver.lock().audio_changed(device));
+                }
+                std::thread::sleep(Duration::from_secs(5));
+            });
+        }
+        self
+    }
+
+    pub fn with_storage(
+        self,
+        enable: bool,
+        refresh_time: f32,
+        mount_points: Vec<PathBuf>,
+        levels: Vec<u8>,
+        watch_mounts: bool,
+    ) -> Result<Self> {
+        if enable && self.is_daemon {
+            let storage = Arc::new(StorageManager::new(MOUNTINFO_PATH, mount_points, levels)?);
+            let refresh_time = refresh_interval(refresh_time, 60.0);
+
+            let receiver = self.receiver.clone();
+            let sleep = self.sleep.clone();
+            let usage = storage.clone();
+            std::thread::spawn(move || loop {
+                if !sleep.is_sleeping() {
+                    for (level, usage) in usage.refresh_usage() {
+                        receiver.lock().disk_space_low(level, &usage);
+                    }
+                }
+                std::thread::sleep(refresh_time);
+            });
+
+            if watch_mounts {
+                let receiver = self.receiver.clone();
+                std::thread::spawn(move || {
+                    _ = storage.watch_mounts(|event| receiver.lock().mount_changed(event));
+                });
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn with_hotplug(
+        self,
+        enable: bool,
+        subsystems: Vec<String>,
+        allow: Vec<String>,
+        deny: Vec<String>,
+    ) -> Self {
+        if enable && self.is_daemon {
+            let hotplug = HotplugManager::new(SYSFS_PATH, subsystems, allow, deny);
+            let receiver = self.receiver.clone();
+            std::thread::spawn(move || {
+                _ = hotplug.listen(|event| receiver.lock().hotplug(event));
+            });
+        }
+        self
+    }
+
+    /// The processes are only scanned when a capture device is opened or closed, or a recording
+    /// of the sound server starts or stops
+    pub fn with_privacy(self, enable: bool) -> Self {
+        if enable && self.is_daemon {
+            let (changed, on_change) = std::sync::mpsc::channel();
+
+            let devices = changed.clone();
+            std::thread::spawn(move || {
+                _ = watch_devices(Path::new(DEV_PATH), || _ = devices.send(()));
+            });
+            // Like the volume, the server can start after us or be restarted
+            std::thread::spawn(move || loop {
+                if let Some(mut audio) =
+                    default_socket().and_then(|s| AudioManager::with_capture(s).ok())
+                {
+                    _ = changed.send(());
+                    _ = audio.listen_capture(|| _ = changed.send(()));
+                    _ = changed.send(());
+                }
+                std::thread::sleep(Duration::from_secs(5));
+            });
+
+            let mut privacy = PrivacyManager::new(PROC_PATH, default_socket());
+            let receiver = self.receiver.clone();
+            std::thread::spawn(move || loop {
+                if let Ok(Some(state)) = privacy.refresh() {
+                    receiver.lock().privacy_changed(&state);
+                }
+                if on_change.recv().is_err() {
+                    break;
+                }
+                // A device is opened and closed several times while it starts
+                while on_change.try_recv().is_ok() {}
+            });
+        }
+        self
+    }
+
+    pub fn with_keyboard(self, enable: bool, refresh_time: f32) -> Result<Self> {
+        if enable && self.is_daemon {
+            let keyboard = KeyboardManager::new(SYSFS_CLASS_PATH)?;
+            let refresh_time = refresh_interval(refresh_time, 0.2);
+            let receiver = self.receiver.clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(refresh_time);
+                for (key, active) in keyboard.refresh().unwrap_or_default() {
+                    receiver.lock().lock_changed(key, active);
+                }
+            });
+
+            let receiver = self.receiver.clone();
+            std::thread::spawn(move || {
+                // Not every compositor exposes the layout, the locks still work without it
+                _ = listen_layout(|layout| receiver.lock().layout_changed(layout));
+            });
+        }
+        Ok(self)
+    }
+
+    pub fn with_hwmon(
+        self,
+        enable: bool,
+        refresh_time: f32,
+        sensors: Vec<String>,
+        levels: Vec<u8>,
+        hysteresis: f32,
+    ) -> Self {
+        if enable && self.is_daemon {
+            let hwmon = HwmonManager::new(SYSFS_CLASS_PATH, sensors, levels, hysteresis);
+            let refresh_time = refresh_interval(refresh_time, 5.0);
+            let receiver = self.receiver.clone();
+            let sleep = self.sleep.clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(refresh_time);
+                if sleep.is_sleeping() {
+                    continue;
+                }
+                for (level, sensor) in hwmon.refresh().unwrap_or_default() {
+                    receiver.lock().temperature_alert(level, &sensor);
+                }
+            });
+        }
+        self
+    }
+
+    pub async fn with_mpris(self, enable: bool) -> Result<Self> {
+        if !enable || !self.is_daemon {
+            return Ok(self);
+        }
+        let receiver = self.receiver.clone();
+        let mpris = MprisManager::new(Connection::session().await?, move |now_playing| {
+            receiver.lock().now_playing(now_playing);
+        })
+        .await?;
+
+        let broadcast = ServiceBroadcast {
+            mpris: Some(Arc::new(mpris)),
+            ..self.broadcast
+        };
+        {
+            self.receiver.lock().set_broadcast(broadcast.clone());
+        }
+        Ok(Self { broadcast, ..self })
+    }
+
+    pub async fn with_network(self, enable: bool) -> Result<Self> {
+        if enable && self.is_daemon {
+            let connection = Connection::system().await?;
+            let receiver = self.receiver.clone();
+            tokio::spawn(listen_network(connection, move |event| {
+                receiver.lock().network_changed(event);
+            }));
+        }
+        Ok(self)
+    }
+
+    /// The polling is only paused while suspended when the power events are enabled, without
+    /// the system bus the other services keep working
+    pub async fn with_power(self, enable: bool, watch_lid: bool) -> Self {
+        if !enable || !self.is_daemon {
+            return self;
+        }
+        let connection = match Connection::system().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                eprintln!("Cannot follow the power events: {e}");
+                return self;
+            }
+        };
+        let receiver = self.receiver.clone();
+        let on_event = Arc::new(move |event: &PowerEvent| receiver.lock().power_changed(event));
+
+        let on_logind = on_event.clone();
+        tokio::spawn(listen_logind(
+            connection.clone(),
+            self.sleep.clone(),
+            watch_lid,
+            move |event| on_logind(event),
+        ));
+        tokio::spawn(listen_profiles(connection, move |event| on_event(event)));
+        self
+    }
+
     pub async fn run(&self) {
         loop {
-            if let Some(battery) = self.battery.as_ref() {
+            if let Some(battery) = self.battery.as_ref().filter(|_| !self.sleep.is_sleeping()) {
+                // The levels read before the suspend are stale
+                if self.sleep.take_resumed() {
+                    _ = battery.refresh().await;
+                    self.receiver.lock().resumed(&battery.all());
+                }
                 let mut receiver = self.receiver.lock();
                 self.battery_levels.iter().for_each(|l| {
                     let batteries_below = battery.batteries_below(*l);
@@ -147,8 +443,11 @@ where
                     }
                 });
             }
-            sleep(self.refresh_time).await;
-            if let Some(battery) = self.battery.as_ref() {
+            tokio::select! {
+                _ = sleep(self.refresh_time) => {}
+                _ = self.sleep.wait_resume() => continue,
+            }
+            if let Some(battery) = self.battery.as_ref().filter(|_| !self.sleep.is_sleeping()) {
                 _ = battery.refresh().await;
             }
         }
@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use futures_lite::StreamExt;
+use image::imageops::{resize, FilterType};
+use image::ImageReader;
+use parking_lot::Mutex;
+use zbus::fdo::DBusProxy;
+use zbus::message::Type;
+use zbus::zvariant::OwnedValue;
+use zbus::{proxy, Connection, MatchRule, MessageStream};
+
+use crate::notification::fetch_id;
+use crate::{Icon, Result};
+
+pub const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+#[proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+trait Player {
+    fn play_pause(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+}
+
+/// Playback Status Enum
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+impl From<&str> for PlaybackStatus {
+    fn from(status: &str) -> Self {
+        match status {
+            "Playing" => PlaybackStatus::Playing,
+            "Paused" => PlaybackStatus::Paused,
+            _ => PlaybackStatus::Stopped,
+        }
+    }
+}
+
+/// Track and state of a player
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NowPlaying {
+    id: u32,
+    player: String,
+    title: String,
+    artists: Vec<String>,
+    album: Option<String>,
+    art_url: Option<String>,
+    status: PlaybackStatus,
+}
+
+impl NowPlaying {
+    fn update_metadata(&mut self, mut metadata: HashMap<String, OwnedValue>) {
+        let mut take = |key: &str| metadata.remove(key);
+        self.title = take("xesam:title")
+            .and_then(|v| String::try_from(v).ok())
+            .unwrap_or_default();
+        self.artists = take("xesam:artist")
+            .and_then(|v| Vec::<String>::try_from(v).ok())
+            .unwrap_or_default();
+        self.album = take("xesam:album").and_then(|v| String::try_from(v).ok());
+        self.art_url = take("mpris:artUrl").and_then(|v| String::try_from(v).ok());
+    }
+
+    /// Notification id used by the OSD, the actions over it go to the player
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Name of the player, like `spotify` or `firefox.instance_1_40`
+    pub fn player(&self) -> &str {
+        &self.player
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn artist(&self) -> String {
+        self.artists.join(", ")
+    }
+
+    pub fn album(&self) -> Option<&str> {
+        self.album.as_deref()
+    }
+
+    pub fn status(&self) -> PlaybackStatus {
+        self.status
+    }
+
+    /// Local path of the album art
+    ///
+    /// Remote covers are not downloaded, but most browsers and players cache them and give a file url
+    pub fn art_path(&self) -> Option<PathBuf> {
+        let path = self.art_url.as_deref()?.strip_prefix("file://")?;
+        percent_decode(path).map(PathBuf::from)
+    }
+
+    /// Load the album art, the file usually has no extension so the format is guessed
+    pub fn art(&self, size: f32) -> Option<Icon> {
+        let img = ImageReader::open(self.art_path()?)
+            .ok()?
+            .with_guessed_format()
+            .ok()?
+            .decode()
+            .ok()?;
+        let size = size as u32;
+        Some(Icon::Image(resize(&img, size, size, FilterType::Gaussian)))
+    }
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Mpris Manager Struct
+pub struct MprisManager {
+    id: u32,
+    connection: Connection,
+    /// Unique name of the last player that changed, it receives the actions
+    active: Arc<Mutex<Option<String>>>,
+}
+
+impl MprisManager {
+    /// Create a new MprisManager watching the players of the bus
+    pub async fn new(
+        connection: Connection,
+        on_change: impl Fn(&NowPlaying) + Send + Sync + 'static,
+    ) -> Result<Self> {
+        let id = fetch_id();
+        let dbus = DBusProxy::new(&connection).await?;
+        // Subscribe before listing the names, so no player is missed between both
+        let mut owners = dbus.receive_name_owner_changed().await?;
+        let rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path(MPRIS_PATH)?
+            .arg(0, PLAYER_INTERFACE)?
+            .build();
+        let mut properties = MessageStream::for_match_rule(rule, &connection, None).await?;
+
+        // Signals come from the unique name, so the players are keyed by it
+        let mut players = HashMap::new();
+        for name in dbus.list_names().await? {
+            let Some(player) = name.strip_prefix(MPRIS_PREFIX) else {
+                continue;
+            };
+            let Ok(owner) = dbus.get_name_owner(name.as_ref()).await else {
+                continue;
+            };
+            let mut now_playing = NowPlaying {
+                id,
+                player: player.to_owned(),
+                ..Default::default()
+            };
+            if let Ok(proxy) = PlayerProxy::new(&connection, owner.to_string()).await {
+                if let Ok(metadata) = proxy.metadata().await {
+                    now_playing.update_metadata(metadata);
+                }
+                if let Ok(status) = proxy.playback_status().await {
+                    now_playing.status = status.as_str().into();
+                }
+            }
+            players.insert(owner.to_string(), now_playing);
+        }
+
+        let active = Arc::new(Mutex::new(
+            players
+                .iter()
+                .find(|(_, p)| p.status == PlaybackStatus::Playing)
+                .map(|(owner, _)| owner.clone()),
+        ));
+
+        let last_active = active.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    Some(signal) = owners.next() => {
+                        let Ok(args) = signal.args() else {
+                            continue;
+                        };
+                        let Some(player) = args.name().strip_prefix(MPRIS_PREFIX) else {
+                            continue;
+                        };
+                        if let Some(old) = args.old_owner().as_ref() {
+                            players.remove(old.as_str());
+                            // The actions of a closed player go to the one still playing
+                            let mut active = last_active.lock();
+                            if active.as_deref() == Some(old.as_str()) {
+                                *active = players
+                                    .iter()
+                                    .find(|(_, p)| p.status == PlaybackStatus::Playing)
+                                    .map(|(owner, _)| owner.clone());
+                            }
+                        }
+                        if let Some(new) = args.new_owner().as_ref() {
+                            players.insert(new.to_string(), NowPlaying {
+                                id,
+                                player: player.to_owned(),
+                                ..Default::default()
+                            });
+                        }
+                    }
+                    Some(Ok(msg)) = properties.next() => {
+                        let header = msg.header();
+                        let Some(sender) = header.sender() else {
+                            continue;
+                        };
+                        let Some(now_playing) = players.get_mut(sender.as_str()) else {
+                            continue;
+                        };
+                        let Ok((_, mut changed, _)) = msg
+                            .body()
+                            .deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()
+                        else {
+                            continue;
+                        };
+
+                        let old = now_playing.clone();
+                        if let Some(metadata) = changed
+                            .remove("Metadata")
+                            .and_then(|m| HashMap::<String, OwnedValue>::try_from(m).ok())
+                        {
+                            now_playing.update_metadata(metadata);
+                        }
+                        if let Some(status) = changed
+                            .remove("PlaybackStatus")
+                            .and_then(|s| String::try_from(s).ok())
+                        {
+                            now_playing.status = status.as_str().into();
+                        }
+
+                        // Some players repeat the metadata on every position change
+                        if *now_playing != old && !now_playing.title.is_empty() {
+                            *last_active.lock() = Some(sender.to_string());
+                            on_change(now_playing);
+                        }
+                    }
+                    else => break,
+                }
+            }
+        });
+
+        Ok(Self {
+            id,
+            connection,
+            active,
+        })
+    }
+
+    /// Notification id shared by the now playing OSDs
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Toggle the playback of the last player that changed
+    pub async fn play_pause(&self) -> Result<()> {
+        let Some(owner) = self.active.lock().clone() else {
+            return Ok(());
+        };
+        PlayerProxy::new(&self.connection, owner)
+            .await?
+            .play_pause()
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    use zbus::interface;
+    use zbus::zvariant::Value;
+
+    use super::*;
+    use crate::test_bus::PrivateBus;
+
+    #[derive(Default)]
+    struct FakePlayer {
+        title: String,
+        status: String,
+        toggled: Arc<Mutex<u32>>,
+    }
+
+    #[interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        fn play_pause(&mut self) {
+            *self.toggled.lock() += 1;
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, Value<'static>> {
+            HashMap::from([
+                ("xesam:title".to_owned(), Value::from(self.title.clone())),
+                (
+                    "xesam:artist".to_owned(),
+                    Value::from(vec!["Artist A".to_owned(), "Artist B".to_owned()]),
+                ),
+                (
+                    "mpris:artUrl".to_owned(),
+                    Value::from("file:///tmp/sosd%20art/cover"),
+                ),
+            ])
+        }
+
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            self.status.clone()
+        }
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("/a%20b/c%C3%B1").as_deref(), Some("/a b/cñ"));
+        assert_eq!(percent_decode("/a%2"), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fake_player_private_bus() {
+        let Some(bus) = PrivateBus::new() else {
+            // dbus-daemon is not installed
+            return;
+        };
+
+        let toggled = Arc::new(Mutex::new(0));
+        let player = bus
+            .builder()
+            .name("org.mpris.MediaPlayer2.fake")
+            .unwrap()
+            .serve_at(
+                MPRIS_PATH,
+                FakePlayer {
+                    title: "First".to_owned(),
+                    status: "Paused".to_owned(),
+                    toggled: toggled.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let (tx, rx) = channel();
+        let client = bus.builder().build().await.unwrap();
+        let manager = MprisManager::new(client, move |now_playing| {
+            tx.send(now_playing.clone()).unwrap();
+        })
+        .await
+        .unwrap();
+
+        let iface = player
+            .object_server()
+            .interface::<_, FakePlayer>(MPRIS_PATH)
+            .await
+            .unwrap();
+        {
+            let mut fake = iface.get_mut().await;
+            fake.title = "Second".to_owned();
+            fake.status = "Playing".to_owned();
+            fake.metadata_changed(iface.signal_emitter()).await.unwrap();
+            fake.playback_status_changed(iface.signal_emitter())
+                .await
+                .unwrap();
+        }
+
+        let now_playing = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(now_playing.id(), manager.id());
+        assert_eq!(now_playing.player(), "fake");
+        assert_eq!(now_playing.title(), "Second");
+        assert_eq!(now_playing.artist(), "Artist A, Artist B");
+        assert_eq!(
+            now_playing.art_path(),
+            Some(PathBuf::from("/tmp/sosd art/cover"))
+        );
+
+        manager.play_pause().await.unwrap();
+        assert_eq!(*toggled.lock(), 1);
+
+        // Once the player quits there is no one left to receive the actions
+        drop(iface);
+        drop(player);
+        for _ in 0..50 {
+            if manager.active.lock().is_none() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(*manager.active.lock(), None);
+        manager.play_pause().await.unwrap();
+    }
+}
@@ -0,0 +1,446 @@
+use std::collections::HashMap;
+
+use futures_lite::StreamExt;
+use zbus::message::Type;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{proxy, Connection, MatchRule, MessageStream};
+
+use crate::Result;
+
+const ACTIVE_INTERFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+/// NM_STATE_DISCONNECTED, below it is asleep or unknown
+const STATE_DISCONNECTED: u32 = 20;
+/// NM_ACTIVE_CONNECTION_STATE_ACTIVATED
+const ACTIVE_STATE_ACTIVATED: u32 = 2;
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+trait NetworkManager {
+    // Renamed, the `State` property already has a `state_changed` stream
+    #[zbus(signal, name = "StateChanged")]
+    fn state_signal(&self, state: u32) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn active_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Connection.Active",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait ActiveConnection {
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[zbus(property, name = "Type")]
+    fn connection_type(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<u32>;
+
+    /// The access point of a wifi connection
+    #[zbus(property)]
+    fn specific_object(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.AccessPoint",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait AccessPoint {
+    #[zbus(property)]
+    fn ssid(&self) -> zbus::Result<Vec<u8>>;
+
+    #[zbus(property)]
+    fn strength(&self) -> zbus::Result<u8>;
+}
+
+/// Network Kind Enum
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum NetworkKind {
+    Wifi,
+    Ethernet,
+    Vpn,
+    Other,
+}
+
+impl From<&str> for NetworkKind {
+    fn from(connection_type: &str) -> Self {
+        match connection_type {
+            "802-11-wireless" => NetworkKind::Wifi,
+            "802-3-ethernet" => NetworkKind::Ethernet,
+            "vpn" | "wireguard" => NetworkKind::Vpn,
+            _ => NetworkKind::Other,
+        }
+    }
+}
+
+/// An activated connection
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkConnection {
+    kind: NetworkKind,
+    name: String,
+    strength: Option<u8>,
+}
+
+impl NetworkConnection {
+    pub fn kind(&self) -> NetworkKind {
+        self.kind
+    }
+
+    /// The SSID of a wifi, otherwise the connection name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Signal strength of a wifi, from 0 to 100
+    pub fn strength(&self) -> Option<u8> {
+        self.strength
+    }
+}
+
+/// Network Event Enum
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkEvent {
+    Connected(NetworkConnection),
+    Disconnected(NetworkConnection),
+    Offline,
+}
+
+async fn get_connection(
+    connection: &Connection,
+    path: OwnedObjectPath,
+) -> zbus::Result<Option<NetworkConnection>> {
+    let active = ActiveConnectionProxy::new(connection, path).await?;
+    if active.state().await? != ACTIVE_STATE_ACTIVATED {
+        return Ok(None);
+    }
+    let kind = NetworkKind::from(active.connection_type().await?.as_str());
+    let mut network = NetworkConnection {
+        kind,
+        name: active.id().await?,
+        strength: None,
+    };
+
+    if kind == NetworkKind::Wifi {
+        let access_point = AccessPointProxy::new(connection, active.specific_object().await?).await;
+        if let Ok(access_point) = access_point {
+            if let Ok(ssid) = access_point.ssid().await {
+                network.name = String::from_utf8_lossy(&ssid).into_owned();
+            }
+            network.strength = access_point.strength().await.ok();
+        }
+    }
+    Ok(Some(network))
+}
+
+/// Activated connections keyed by their path
+async fn get_connections(
+    connection: &Connection,
+    nm: &NetworkManagerProxy<'_>,
+) -> zbus::Result<HashMap<OwnedObjectPath, NetworkConnection>> {
+    let mut connections = HashMap::new();
+    for path in nm.active_connections().await? {
+        // The connection can go away while it is read
+        if let Ok(Some(network)) = get_connection(connection, path.clone()).await {
+            connections.insert(path, network);
+        }
+    }
+    Ok(connections)
+}
+
+/// Compare both snapshots, the disconnections go first
+fn diff_connections(
+    old: &HashMap<OwnedObjectPath, NetworkConnection>,
+    new: &HashMap<OwnedObjectPath, NetworkConnection>,
+) -> Vec<NetworkEvent> {
+    let disconnected = old
+        .iter()
+        .filter(|(path, _)| !new.contains_key(*path))
+        .map(|(_, network)| NetworkEvent::Disconnected(network.clone()));
+    let connected = new
+        .iter()
+        .filter(|(path, _)| !old.contains_key(*path))
+        .map(|(_, network)| NetworkEvent::Connected(network.clone()));
+    disconnected.chain(connected).collect()
+}
+
+/// Watch the connections of NetworkManager until the bus is closed
+pub async fn listen_network(
+    connection: Connection,
+    on_event: impl Fn(&NetworkEvent) + Send + Sync + 'static,
+) -> Result<()> {
+    let nm = NetworkManagerProxy::new(&connection).await?;
+    // The properties are read right after the signals, before the cache would be updated
+    let current = NetworkManagerProxy::builder(&connection)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    let mut states = nm.receive_state_signal().await?;
+    let mut active_changes = nm.receive_active_connections_changed().await;
+    // Activating connections are already listed, they are shown once activated
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .interface(ACTIVE_INTERFACE)?
+        .member("StateChanged")?
+        .build();
+    let mut activations = MessageStream::for_match_rule(rule, &connection, None).await?;
+
+    let mut online = current.state().await? > STATE_DISCONNECTED;
+    let mut connections = get_connections(&connection, &current).await?;
+
+    loop {
+        tokio::select! {
+            Some(_) = states.next() => {}
+            Some(_) = active_changes.next() => {}
+            Some(_) = activations.next() => {}
+            else => return Ok(()),
+        }
+
+        let Ok(new) = get_connections(&connection, &current).await else {
+            continue;
+        };
+        let is_online = current
+            .state()
+            .await
+            .map_or(online, |state| state > STATE_DISCONNECTED);
+        let went_offline = online && !is_online;
+        online = is_online;
+
+        // Going offline drops every connection, it is shown once instead of each of them, and
+        // not at all when they were already shown going down
+        if went_offline {
+            if !connections.is_empty() {
+                on_event(&NetworkEvent::Offline);
+            }
+        } else {
+            for event in diff_connections(&connections, &new) {
+                on_event(&event);
+            }
+        }
+        connections = new;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    use zbus::interface;
+    use zbus::object_server::SignalEmitter;
+    use zbus::zvariant::ObjectPath;
+
+    use super::*;
+    use crate::test_bus::PrivateBus;
+
+    const NM_SERVICE: &str = "org.freedesktop.NetworkManager";
+    const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+    const WIFI_PATH: &str = "/org/freedesktop/NetworkManager/ActiveConnection/1";
+    const VPN_PATH: &str = "/org/freedesktop/NetworkManager/ActiveConnection/2";
+    const AP_PATH: &str = "/org/freedesktop/NetworkManager/AccessPoint/1";
+
+    struct FakeNetworkManager {
+        state: u32,
+        active: Vec<OwnedObjectPath>,
+    }
+
+    #[interface(name = "org.freedesktop.NetworkManager")]
+    impl FakeNetworkManager {
+        #[zbus(signal, name = "StateChanged")]
+        async fn state_signal(emitter: &SignalEmitter<'_>, state: u32) -> zbus::Result<()>;
+
+        #[zbus(property)]
+        fn state(&self) -> u32 {
+            self.state
+        }
+
+        #[zbus(property)]
+        fn active_connections(&self) -> Vec<OwnedObjectPath> {
+            self.active.clone()
+        }
+    }
+
+    struct FakeActiveConnection {
+        id: &'static str,
+        kind: &'static str,
+        state: u32,
+    }
+
+    #[interface(name = "org.freedesktop.NetworkManager.Connection.Active")]
+    impl FakeActiveConnection {
+        #[zbus(signal, name = "StateChanged")]
+        async fn state_signal(
+            emitter: &SignalEmitter<'_>,
+            state: u32,
+            reason: u32,
+        ) -> zbus::Result<()>;
+
+        #[zbus(property)]
+        fn id(&self) -> &str {
+            self.id
+        }
+
+        #[zbus(property, name = "Type")]
+        fn connection_type(&self) -> &str {
+            self.kind
+        }
+
+        #[zbus(property)]
+        fn state(&self) -> u32 {
+            self.state
+        }
+
+        #[zbus(property)]
+        fn specific_object(&self) -> ObjectPath<'_> {
+            ObjectPath::from_static_str_unchecked(AP_PATH)
+        }
+    }
+
+    struct FakeAccessPoint;
+
+    #[interface(name = "org.freedesktop.NetworkManager.AccessPoint")]
+    impl FakeAccessPoint {
+        #[zbus(property)]
+        fn ssid(&self) -> Vec<u8> {
+            b"Home".to_vec()
+        }
+
+        #[zbus(property)]
+        fn strength(&self) -> u8 {
+            72
+        }
+    }
+
+    fn path(path: &'static str) -> OwnedObjectPath {
+        ObjectPath::from_static_str_unchecked(path).into()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mock_network_manager() {
+        let Some(bus) = PrivateBus::new() else {
+            // dbus-daemon is not installed
+            return;
+        };
+        let nm = bus
+            .builder()
+            .name(NM_SERVICE)
+            .unwrap()
+            .serve_at(
+                NM_PATH,
+                FakeNetworkManager {
+                    state: 70,
+                    active: vec![],
+                },
+            )
+            .unwrap()
+            .serve_at(AP_PATH, FakeAccessPoint)
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let (tx, rx) = channel();
+        let client = bus.builder().build().await.unwrap();
+        tokio::spawn(listen_network(client, move |event| {
+            tx.send(event.clone()).unwrap();
+        }));
+        // Let the listener subscribe before changing anything
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let server = nm.object_server();
+        let manager = server
+            .interface::<_, FakeNetworkManager>(NM_PATH)
+            .await
+            .unwrap();
+
+        // The wifi is listed while activating, but only shown once activated
+        server
+            .at(
+                WIFI_PATH,
+                FakeActiveConnection {
+                    id: "Home connection",
+                    kind: "802-11-wireless",
+                    state: 1,
+                },
+            )
+            .await
+            .unwrap();
+        {
+            let mut fake = manager.get_mut().await;
+            fake.active.push(path(WIFI_PATH));
+            fake.active_connections_changed(manager.signal_emitter())
+                .await
+                .unwrap();
+        }
+        let wifi = server
+            .interface::<_, FakeActiveConnection>(WIFI_PATH)
+            .await
+            .unwrap();
+        wifi.get_mut().await.state = ACTIVE_STATE_ACTIVATED;
+        FakeActiveConnection::state_signal(wifi.signal_emitter(), ACTIVE_STATE_ACTIVATED, 0)
+            .await
+            .unwrap();
+
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        let NetworkEvent::Connected(network) = event else {
+            panic!("Expected a connection, got {event:?}");
+        };
+        assert_eq!(network.kind(), NetworkKind::Wifi);
+        assert_eq!(network.name(), "Home");
+        assert_eq!(network.strength(), Some(72));
+
+        server
+            .at(
+                VPN_PATH,
+                FakeActiveConnection {
+                    id: "Work",
+                    kind: "wireguard",
+                    state: ACTIVE_STATE_ACTIVATED,
+                },
+            )
+            .await
+            .unwrap();
+        {
+            let mut fake = manager.get_mut().await;
+            fake.active.push(path(VPN_PATH));
+            fake.active_connections_changed(manager.signal_emitter())
+                .await
+                .unwrap();
+        }
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            event,
+            NetworkEvent::Connected(NetworkConnection {
+                kind: NetworkKind::Vpn,
+                name: "Work".to_owned(),
+                strength: None,
+            })
+        );
+
+        {
+            let mut fake = manager.get_mut().await;
+            fake.active.clear();
+            fake.state = STATE_DISCONNECTED;
+            FakeNetworkManager::state_signal(manager.signal_emitter(), STATE_DISCONNECTED)
+                .await
+                .unwrap();
+            fake.active_connections_changed(manager.signal_emitter())
+                .await
+                .unwrap();
+        }
+        // The state and the connections change at the same time, only going offline is shown
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            NetworkEvent::Offline
+        );
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+    }
+}
@@ -4,9 +4,28 @@ use zbus::fdo::Result;
 mod bus;
 mod icon;
 
+pub(crate) use bus::fetch_id;
 pub use bus::{NotificationIPC, NotificationIPCSignals};
 pub use icon::Icon;
 
+/// How long a message stays on screen when it does not follow the urgency
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timeout {
+    /// Seconds, a fraction is kept
+    Seconds(f32),
+    /// Until it is closed, like the resident notifications
+    Never,
+}
+
+/// Why a notification was closed, sent to its client with `NotificationClosed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    /// Closed by the user, or by invoking one of its actions
+    Dismissed = 2,
+    /// Closed by a call to `CloseNotification`
+    Closed = 3,
+}
+
 pub trait Notification {
     fn get_icon_size(&self) -> f32 {
         18.0
@@ -20,7 +39,7 @@ pub trait Notification {
             //"body-markup",
             // "icon-multi",
             "icon-static",
-            //"persistence",
+            "persistence",
             //"sound",
         ];
 
@@ -40,13 +59,18 @@ pub trait Notification {
     fn notify(
         &mut self,
         id: u32,
+        // Name of the application, or its desktop entry when the name is empty
+        app: Option<String>,
         summary: String,
         icon: Option<Icon>,
         urgency: Urgency,
         body: Option<String>,
         value: Option<f32>,
         actions: Vec<String>,
-        expire_timeout: Option<i32>,
+        // `None` is the default of the server
+        expire_timeout: Option<Timeout>,
+        // Kept on screen when one of its actions is invoked
+        resident: bool,
     ) -> Result<u32>;
 
     fn close_notification(&mut self, id: u32) -> Result<()>;
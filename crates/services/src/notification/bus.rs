@@ -11,10 +11,10 @@ use zbus::object_server::SignalEmitter;
 
 use crate::Icon;
 
-use super::Notification;
+use super::{Notification, Timeout};
 
 static ID_COUNT: AtomicU32 = AtomicU32::new(1);
-fn fetch_id() -> u32 {
+pub(crate) fn fetch_id() -> u32 {
     ID_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
 }
 
@@ -60,7 +60,7 @@ impl<T: Notification + 'static> NotificationIPC<T> {
     #[allow(clippy::too_many_arguments)]
     fn notify(
         &self,
-        _app_name: String,
+        app_name: String,
         replaces_id: u32,
         app_icon: String,
         summary: String,
@@ -113,12 +113,26 @@ impl<T: Notification + 'static> NotificationIPC<T> {
             None
         });
 
-        let timeout = if expire_timeout <= 0 {
-            None
-        } else {
-            Some(expire_timeout)
+        // A timeout of `0` keeps it on screen until it is closed, like the resident ones
+        let resident = hints
+            .get("resident")
+            .and_then(|r| r.clone().downcast::<bool>().ok())
+            .unwrap_or_default();
+        let timeout = match expire_timeout {
+            _ if resident => Some(Timeout::Never),
+            0 => Some(Timeout::Never),
+            t if t < 0 => None,
+            t => Some(Timeout::Seconds(t as f32 / 1000.0)),
         };
 
+        // Some clients only send the desktop entry, like `org.telegram.desktop`
+        let app = app_name.is_empty().not().then_some(app_name).or_else(|| {
+            hints
+                .get("desktop-entry")
+                .and_then(|d| d.clone().downcast::<String>().ok())
+                .filter(|d| !d.is_empty())
+        });
+
         let urgency = hints
             .get("urgency")
             .and_then(|u| u.clone().downcast::<u8>().ok())
@@ -139,6 +153,7 @@ impl<T: Notification + 'static> NotificationIPC<T> {
 
             return inner.notify(
                 id,
+                app,
                 summary,
                 icon,
                 urgency,
@@ -146,10 +161,13 @@ impl<T: Notification + 'static> NotificationIPC<T> {
                 Some(value),
                 actions,
                 timeout,
+                resident,
             );
         }
 
-        inner.notify(id, summary, icon, urgency, body, None, actions, timeout)
+        inner.notify(
+            id, app, summary, icon, urgency, body, None, actions, timeout, resident,
+        )
     }
 
     fn close_notification(&self, id: u32) -> Result<()> {
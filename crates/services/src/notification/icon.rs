@@ -6,7 +6,7 @@ use zbus::zvariant::{Structure, Value};
 
 use crate::error::IconError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Icon {
     Char(char),
     Image(RgbaImage),
@@ -0,0 +1,289 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_lite::StreamExt;
+use tokio::sync::Notify;
+use zbus::proxy::CacheProperties;
+use zbus::{proxy, Connection};
+
+use crate::Result;
+
+/// How often the lid is read, logind does not signal its changes
+const LID_REFRESH: Duration = Duration::from_secs(2);
+
+#[proxy(
+    interface = "net.hadess.PowerProfiles",
+    default_service = "net.hadess.PowerProfiles",
+    default_path = "/net/hadess/PowerProfiles"
+)]
+trait PowerProfiles {
+    #[zbus(property)]
+    fn active_profile(&self) -> zbus::Result<String>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Login {
+    #[zbus(signal)]
+    fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn lid_closed(&self) -> zbus::Result<bool>;
+}
+
+/// Power Profile Enum
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum PowerProfile {
+    PowerSaver,
+    Balanced,
+    Performance,
+    Other(String),
+}
+
+impl From<String> for PowerProfile {
+    fn from(profile: String) -> Self {
+        match profile.as_str() {
+            "power-saver" => PowerProfile::PowerSaver,
+            "balanced" => PowerProfile::Balanced,
+            "performance" => PowerProfile::Performance,
+            _ => PowerProfile::Other(profile),
+        }
+    }
+}
+
+/// Power Event Enum
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum PowerEvent {
+    Profile(PowerProfile),
+    Lid { closed: bool },
+    Sleep,
+    Resume,
+}
+
+/// Shared between the logind listener and the polling of `ServiceManager::run`
+#[derive(Debug, Default, Clone)]
+pub struct SleepState {
+    sleeping: Arc<AtomicBool>,
+    resumed: Arc<AtomicBool>,
+    wake: Arc<Notify>,
+}
+
+impl SleepState {
+    pub fn is_sleeping(&self) -> bool {
+        self.sleeping.load(Ordering::Relaxed)
+    }
+
+    /// True only the first time it is called after a resume
+    pub fn take_resumed(&self) -> bool {
+        self.resumed.swap(false, Ordering::Relaxed)
+    }
+
+    /// Wait until the next resume
+    pub async fn wait_resume(&self) {
+        self.wake.notified().await;
+    }
+
+    fn set_sleeping(&self, sleeping: bool) {
+        self.sleeping.store(sleeping, Ordering::Relaxed);
+        if !sleeping {
+            self.resumed.store(true, Ordering::Relaxed);
+            self.wake.notify_one();
+        }
+    }
+}
+
+/// Watch the active profile of power-profiles-daemon
+pub async fn listen_profiles(
+    connection: Connection,
+    on_event: impl Fn(&PowerEvent) + Send + Sync + 'static,
+) -> Result<()> {
+    let profiles = PowerProfilesProxy::new(&connection).await?;
+    let mut last = profiles.active_profile().await?;
+    let mut changes = profiles.receive_active_profile_changed().await;
+
+    while let Some(change) = changes.next().await {
+        let Ok(profile) = change.get().await else {
+            continue;
+        };
+        // The stream starts with the current value
+        if profile != last {
+            last = profile.clone();
+            on_event(&PowerEvent::Profile(profile.into()));
+        }
+    }
+    Ok(())
+}
+
+/// Watch the suspend and resume from logind, and the lid when `watch_lid`
+pub async fn listen_logind(
+    connection: Connection,
+    state: SleepState,
+    watch_lid: bool,
+    on_event: impl Fn(&PowerEvent) + Send + Sync + 'static,
+) -> Result<()> {
+    let login = LoginProxy::builder(&connection)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    let mut sleeps = login.receive_prepare_for_sleep().await?;
+    let mut lid_closed = if watch_lid {
+        login.lid_closed().await.ok()
+    } else {
+        None
+    };
+
+    loop {
+        tokio::select! {
+            Some(signal) = sleeps.next() => {
+                let Ok(args) = signal.args() else {
+                    continue;
+                };
+                state.set_sleeping(args.start);
+                on_event(if args.start { &PowerEvent::Sleep } else { &PowerEvent::Resume });
+            }
+            _ = tokio::time::sleep(LID_REFRESH), if lid_closed.is_some() => {
+                let Ok(closed) = login.lid_closed().await else {
+                    continue;
+                };
+                if lid_closed != Some(closed) {
+                    lid_closed = Some(closed);
+                    on_event(&PowerEvent::Lid { closed });
+                }
+            }
+            else => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use zbus::interface;
+    use zbus::object_server::SignalEmitter;
+
+    use super::*;
+    use crate::test_bus::PrivateBus;
+
+    struct FakePowerProfiles(String);
+
+    #[interface(name = "net.hadess.PowerProfiles")]
+    impl FakePowerProfiles {
+        #[zbus(property)]
+        fn active_profile(&self) -> String {
+            self.0.clone()
+        }
+    }
+
+    struct FakeLogin {
+        lid_closed: bool,
+    }
+
+    #[interface(name = "org.freedesktop.login1.Manager")]
+    impl FakeLogin {
+        #[zbus(signal)]
+        async fn prepare_for_sleep(emitter: &SignalEmitter<'_>, start: bool) -> zbus::Result<()>;
+
+        #[zbus(property)]
+        fn lid_closed(&self) -> bool {
+            self.lid_closed
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_profiles_and_sleep() {
+        let Some(bus) = PrivateBus::new() else {
+            // dbus-daemon is not installed
+            return;
+        };
+        let profiles = bus
+            .builder()
+            .name("net.hadess.PowerProfiles")
+            .unwrap()
+            .serve_at(
+                "/net/hadess/PowerProfiles",
+                FakePowerProfiles("balanced".to_owned()),
+            )
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        let login = bus
+            .builder()
+            .name("org.freedesktop.login1")
+            .unwrap()
+            .serve_at("/org/freedesktop/login1", FakeLogin { lid_closed: false })
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let (tx, rx) = channel();
+        let client = bus.builder().build().await.unwrap();
+        let state = SleepState::default();
+        let profile_tx = tx.clone();
+        tokio::spawn(listen_profiles(client.clone(), move |event| {
+            profile_tx.send(event.clone()).unwrap();
+        }));
+        tokio::spawn(listen_logind(client, state.clone(), true, move |event| {
+            tx.send(event.clone()).unwrap();
+        }));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let iface = profiles
+            .object_server()
+            .interface::<_, FakePowerProfiles>("/net/hadess/PowerProfiles")
+            .await
+            .unwrap();
+        {
+            let mut fake = iface.get_mut().await;
+            fake.0 = "power-saver".to_owned();
+            fake.active_profile_changed(iface.signal_emitter())
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            PowerEvent::Profile(PowerProfile::PowerSaver)
+        );
+
+        let iface = login
+            .object_server()
+            .interface::<_, FakeLogin>("/org/freedesktop/login1")
+            .await
+            .unwrap();
+        FakeLogin::prepare_for_sleep(iface.signal_emitter(), true)
+            .await
+            .unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            PowerEvent::Sleep
+        );
+        assert!(state.is_sleeping());
+        assert!(!state.take_resumed());
+
+        iface.get_mut().await.lid_closed = true;
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            PowerEvent::Lid { closed: true }
+        );
+
+        FakeLogin::prepare_for_sleep(iface.signal_emitter(), false)
+            .await
+            .unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            PowerEvent::Resume
+        );
+        assert!(!state.is_sleeping());
+        tokio::time::timeout(Duration::from_secs(1), state.wait_resume())
+            .await
+            .unwrap();
+        assert!(state.take_resumed());
+        assert!(!state.take_resumed());
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+
+use crate::notification::fetch_id;
+use crate::{AudioManager, Result};
+
+pub const PROC_PATH: &str = "/proc";
+pub const DEV_PATH: &str = "/dev";
+
+/// Sound servers keep the capture device open for their clients, the clients are named instead
+const SOUND_SERVERS: [&str; 3] = ["pipewire", "pulseaudio", "wireplumber"];
+
+/// Capture Device Kind Enum
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum CaptureKind {
+    Microphone,
+    Camera,
+}
+
+impl CaptureKind {
+    /// From the name of a device node, like `video0` or `pcmC0D0c`
+    fn from_node(name: &str) -> Option<Self> {
+        if name.starts_with("video") {
+            Some(CaptureKind::Camera)
+        } else if name.strip_prefix("pcmC")?.ends_with('c') {
+            // The playback devices end with `p`
+            Some(CaptureKind::Microphone)
+        } else {
+            None
+        }
+    }
+
+    /// From the target of an open file, like `/dev/video0` or `/dev/snd/pcmC0D0c`
+    fn from_device(path: &Path) -> Option<Self> {
+        match path.parent()?.to_str()? {
+            "/dev" | "/dev/snd" => Self::from_node(path.file_name()?.to_str()?),
+            _ => None,
+        }
+    }
+}
+
+/// Applications capturing each device
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CaptureState {
+    id: u32,
+    microphone: BTreeSet<String>,
+    camera: BTreeSet<String>,
+}
+
+impl CaptureState {
+    /// Notification id used by the indicator
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn microphone(&self) -> impl Iterator<Item = &str> {
+        self.microphone.iter().map(String::as_str)
+    }
+
+    pub fn camera(&self) -> impl Iterator<Item = &str> {
+        self.camera.iter().map(String::as_str)
+    }
+
+    pub fn is_active(&self, kind: CaptureKind) -> bool {
+        match kind {
+            CaptureKind::Microphone => !self.microphone.is_empty(),
+            CaptureKind::Camera => !self.camera.is_empty(),
+        }
+    }
+
+    /// Every application capturing something, without repeating them
+    pub fn applications(&self) -> Vec<&str> {
+        self.microphone
+            .union(&self.camera)
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Find the processes below `root` with a capture device open
+///
+/// Only the processes of the same user can be read, the others are skipped
+pub fn scan_processes(root: &Path) -> Result<CaptureState> {
+    let mut state = CaptureState::default();
+    for process in fs::read_dir(root)?.filter_map(|e| e.ok()) {
+        if process
+            .file_name()
+            .to_string_lossy()
+            .parse::<u32>()
+            .is_err()
+        {
+            continue;
+        }
+        let Ok(fds) = fs::read_dir(process.path().join("fd")) else {
+            continue;
+        };
+        let kinds: Vec<_> = fds
+            .filter_map(|fd| fs::read_link(fd.ok()?.path()).ok())
+            .filter_map(|target| CaptureKind::from_device(&target))
+            .collect();
+        if kinds.is_empty() {
+            continue;
+        }
+
+        let Ok(name) = fs::read_to_string(process.path().join("comm")) else {
+            continue;
+        };
+        let name = name.trim().to_owned();
+        if kinds.contains(&CaptureKind::Camera) {
+            state.camera.insert(name.clone());
+        }
+        if kinds.contains(&CaptureKind::Microphone) {
+            state.microphone.insert(name);
+        }
+    }
+    Ok(state)
+}
+
+/// Block calling `on_change` each time a capture device below `root` is opened or closed, the
+/// devices plugged later are followed too
+///
+/// The sound servers keep the microphone open for their clients, the recordings through them
+/// are followed with [`AudioManager::listen_capture`]
+pub fn watch_devices(root: &Path, on_change: impl Fn()) -> Result<()> {
+    let inotify = Inotify::init(InitFlags::IN_CLOEXEC)?;
+    let access = AddWatchFlags::IN_OPEN | AddWatchFlags::IN_CLOSE;
+    let mut dirs = HashMap::new();
+
+    for dir in [root.to_path_buf(), root.join("snd")] {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        // Like the processes, only the devices of the user can be watched
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name();
+            if name.to_str().and_then(CaptureKind::from_node).is_some() {
+                _ = inotify.add_watch(&entry.path(), access);
+            }
+        }
+        dirs.insert(inotify.add_watch(&dir, AddWatchFlags::IN_CREATE)?, dir);
+    }
+
+    loop {
+        for event in inotify.read_events()? {
+            let Some(dir) = dirs.get(&event.wd) else {
+                if event.mask.intersects(access) {
+                    on_change();
+                }
+                continue;
+            };
+            let Some(name) = event.name.as_ref().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if CaptureKind::from_node(name).is_some() {
+                _ = inotify.add_watch(&dir.join(name), access);
+            }
+        }
+    }
+}
+
+/// Privacy Manager Struct
+pub struct PrivacyManager {
+    id: u32,
+    root: PathBuf,
+    /// Socket of the sound server, the applications are named through it when it is running
+    audio_socket: Option<PathBuf>,
+    audio: Option<AudioManager>,
+    last: CaptureState,
+}
+
+impl PrivacyManager {
+    /// Create a new PrivacyManager reading the processes below `root`
+    pub fn new(root: impl Into<PathBuf>, audio_socket: Option<PathBuf>) -> Self {
+        let id = fetch_id();
+        Self {
+            id,
+            root: root.into(),
+            audio_socket,
+            audio: None,
+            last: CaptureState {
+                id,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Applications recording from the sound server, reconnecting when it was restarted
+    fn capture_applications(&mut self) -> Option<Vec<String>> {
+        if self.audio.is_none() {
+            self.audio = AudioManager::connect(self.audio_socket.as_ref()?).ok();
+        }
+        let applications = self.audio.as_mut()?.capture_applications();
+        if applications.is_err() {
+            self.audio = None;
+        }
+        applications.ok()
+    }
+
+    /// Return the new state when an application starts or stops capturing
+    pub fn refresh(&mut self) -> Result<Option<CaptureState>> {
+        let mut state = scan_processes(&self.root)?;
+        state.id = self.id;
+        if let Some(applications) = self.capture_applications() {
+            state
+                .microphone
+                .retain(|name| !SOUND_SERVERS.contains(&name.as_str()));
+            state.microphone.extend(applications);
+        }
+
+        if state == self.last {
+            return Ok(None);
+        }
+        self.last = state.clone();
+        Ok(Some(state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn fake_process(root: &Path, pid: u32, name: &str, devices: &[&str]) {
+        let fd = root.join(pid.to_string()).join("fd");
+        _ = fs::remove_dir_all(&fd);
+        fs::create_dir_all(&fd).unwrap();
+        fs::write(root.join(pid.to_string()).join("comm"), format!("{name}\n")).unwrap();
+        for (i, device) in ["/dev/null", "socket:[4242]"]
+            .iter()
+            .chain(devices)
+            .enumerate()
+        {
+            symlink(device, fd.join(i.to_string())).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_scan_processes() {
+        let root = std::env::temp_dir().join(format!("sosd-privacy-{}", std::process::id()));
+        _ = fs::remove_dir_all(&root);
+        fake_process(&root, 100, "firefox", &["/dev/video0", "/dev/snd/pcmC1D0c"]);
+        fake_process(&root, 200, "mpv", &["/dev/snd/pcmC0D0p"]);
+        fake_process(&root, 300, "arecord", &["/dev/snd/pcmC0D0c"]);
+        fs::create_dir_all(root.join("self")).unwrap();
+
+        let mut manager = PrivacyManager::new(&root, None);
+        let state = manager.refresh().unwrap().unwrap();
+        assert_eq!(state.id(), manager.id);
+        assert_eq!(state.camera().collect::<Vec<_>>(), ["firefox"]);
+        assert_eq!(
+            state.microphone().collect::<Vec<_>>(),
+            ["arecord", "firefox"]
+        );
+        assert_eq!(state.applications(), ["arecord", "firefox"]);
+        assert_eq!(manager.refresh().unwrap(), None);
+
+        // Closing the devices is a change
+        fake_process(&root, 100, "firefox", &[]);
+        fs::remove_dir_all(root.join("300")).unwrap();
+        let state = manager.refresh().unwrap().unwrap();
+        assert!(!state.is_active(CaptureKind::Camera));
+        assert!(!state.is_active(CaptureKind::Microphone));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_watch_devices() {
+        let root = std::env::temp_dir().join(format!("sosd-privacy-dev-{}", std::process::id()));
+        _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("snd")).unwrap();
+        for node in ["video0", "null", "snd/pcmC0D0p"] {
+            fs::write(root.join(node), "").unwrap();
+        }
+        let (tx, rx) = channel();
+        {
+            let root = root.clone();
+            std::thread::spawn(move || _ = watch_devices(&root, || _ = tx.send(())));
+        }
+        std::thread::sleep(Duration::from_millis(100));
+
+        // Neither the playback nor the other devices
+        fs::File::open(root.join("null")).unwrap();
+        fs::File::open(root.join("snd/pcmC0D0p")).unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+        fs::File::open(root.join("video0")).unwrap();
+        rx.recv_timeout(Duration::from_secs(2)).unwrap();
+
+        // A microphone plugged later
+        fs::write(root.join("snd/pcmC1D0c"), "").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        while rx.try_recv().is_ok() {}
+        fs::File::open(root.join("snd/pcmC1D0c")).unwrap();
+        rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        fs::remove_dir_all(root).unwrap();
+    }
+}
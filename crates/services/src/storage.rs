@@ -0,0 +1,371 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::os::fd::AsFd;
+use std::path::{Path, PathBuf};
+
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use nix::sys::statvfs::statvfs;
+use parking_lot::Mutex;
+
+use crate::Result;
+
+pub const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+/// Where udisks and most desktops mount the removable media
+const REMOVABLE_PREFIXES: [&str; 3] = ["/run/media/", "/media/", "/mnt/"];
+
+/// A mounted filesystem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    source: String,
+    mount_point: PathBuf,
+    fs_type: String,
+}
+
+impl Mount {
+    /// The device or server, like `/dev/sdb1`
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn mount_point(&self) -> &Path {
+        &self.mount_point
+    }
+
+    pub fn fs_type(&self) -> &str {
+        &self.fs_type
+    }
+
+    /// Name shown in the OSD, the last component of the mount point like the label of the media
+    pub fn name(&self) -> String {
+        self.mount_point
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.mount_point.to_string_lossy().into_owned())
+    }
+
+    pub fn is_removable(&self) -> bool {
+        let mount_point = self.mount_point.to_string_lossy();
+        REMOVABLE_PREFIXES
+            .iter()
+            .any(|prefix| mount_point.starts_with(prefix))
+    }
+}
+
+/// Mount Event Enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MountEvent {
+    Mounted(Mount),
+    Unmounted(Mount),
+}
+
+/// The spaces and other characters are escaped as octal, like `\040`
+fn unescape(field: &str) -> String {
+    let mut bytes = Vec::with_capacity(field.len());
+    let mut iter = field.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'\\' {
+            let octal: Vec<u8> = (0..3).filter_map(|_| iter.next()).collect();
+            match std::str::from_utf8(&octal)
+                .ok()
+                .and_then(|o| u8::from_str_radix(o, 8).ok())
+            {
+                Some(c) => bytes.push(c),
+                None => {
+                    bytes.push(b);
+                    bytes.extend(octal);
+                }
+            }
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Parse the lines of `/proc/self/mountinfo`
+///
+/// `36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue`
+pub fn parse_mountinfo(content: &str) -> Vec<Mount> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let mount_point = fields.nth(4)?;
+            // The optional fields end with a lone dash
+            let mut fields = fields.skip_while(|f| *f != "-").skip(1);
+            let fs_type = fields.next()?;
+            let source = fields.next()?;
+            Some(Mount {
+                source: unescape(source),
+                mount_point: PathBuf::from(unescape(mount_point)),
+                fs_type: unescape(fs_type),
+            })
+        })
+        .collect()
+}
+
+/// Space of the filesystem of a mount point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskUsage {
+    mount_point: PathBuf,
+    /// Bytes available to unprivileged users
+    available: u64,
+    total: u64,
+}
+
+impl DiskUsage {
+    pub fn mount_point(&self) -> &Path {
+        &self.mount_point
+    }
+
+    pub fn available(&self) -> u64 {
+        self.available
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn percent_free(&self) -> u8 {
+        if self.total == 0 {
+            return 100;
+        }
+        (self.available as f64 / self.total as f64 * 100.0).round() as u8
+    }
+}
+
+/// Read the space with `statvfs`, along with the filesystem id to skip the repeated ones
+fn get_usage(mount_point: &Path) -> Result<(u64, DiskUsage)> {
+    let stat = statvfs(mount_point)?;
+    let fragment = stat.fragment_size() as u64;
+    Ok((
+        stat.filesystem_id() as u64,
+        DiskUsage {
+            mount_point: mount_point.to_owned(),
+            available: stat.blocks_available() as u64 * fragment,
+            total: stat.blocks() as u64 * fragment,
+        },
+    ))
+}
+
+/// Threshold crossings of each mount point
+#[derive(Debug, Default)]
+struct SpaceAlerts {
+    /// Thresholds sorted from the lowest
+    levels: Vec<u8>,
+    /// Lowest threshold reached by each mount point
+    reached: HashMap<PathBuf, u8>,
+}
+
+impl SpaceAlerts {
+    fn new(mut levels: Vec<u8>) -> Self {
+        levels.sort_unstable();
+        Self {
+            levels,
+            reached: HashMap::new(),
+        }
+    }
+
+    /// Return the threshold when the free space goes below a lower one
+    fn update(&mut self, usage: &DiskUsage) -> Option<u8> {
+        let free = usage.percent_free();
+        let current = self.levels.iter().find(|level| free <= **level).copied();
+
+        match (current, self.reached.get(&usage.mount_point).copied()) {
+            (Some(current), Some(reached)) if current >= reached => {
+                // Some space was freed, so the lower thresholds can alert again
+                self.reached.insert(usage.mount_point.clone(), current);
+                None
+            }
+            (Some(current), _) => {
+                self.reached.insert(usage.mount_point.clone(), current);
+                Some(current)
+            }
+            (None, _) => {
+                self.reached.remove(&usage.mount_point);
+                None
+            }
+        }
+    }
+}
+
+/// Storage Manager Struct
+pub struct StorageManager {
+    mountinfo: PathBuf,
+    mount_points: Vec<PathBuf>,
+    mounts: Mutex<Vec<Mount>>,
+    alerts: Mutex<SpaceAlerts>,
+}
+
+impl StorageManager {
+    /// Create a new StorageManager reading the mounts from `mountinfo`
+    pub fn new(
+        mountinfo: impl Into<PathBuf>,
+        mount_points: Vec<PathBuf>,
+        levels: Vec<u8>,
+    ) -> Result<Self> {
+        let mountinfo = mountinfo.into();
+        let mounts = parse_mountinfo(&fs::read_to_string(&mountinfo)?);
+        Ok(Self {
+            mountinfo,
+            mount_points,
+            mounts: Mutex::new(mounts),
+            alerts: Mutex::new(SpaceAlerts::new(levels)),
+        })
+    }
+
+    /// Read the space of the mount points, returning the ones that crossed a threshold with it
+    ///
+    /// Mount points of the same filesystem, like `/home` without its own partition, alert once
+    pub fn refresh_usage(&self) -> Vec<(u8, DiskUsage)> {
+        let mut alerts = self.alerts.lock();
+        let mut filesystems = HashSet::new();
+        self.mount_points
+            .iter()
+            .filter_map(|mount_point| get_usage(mount_point).ok())
+            .filter(|(id, _)| filesystems.insert(*id))
+            .filter_map(|(_, usage)| Some((alerts.update(&usage)?, usage)))
+            .collect()
+    }
+
+    /// Read the mounts again, returning the removable media mounted and unmounted since the last time
+    pub fn refresh_mounts(&self) -> Result<Vec<MountEvent>> {
+        let new = parse_mountinfo(&fs::read_to_string(&self.mountinfo)?);
+        let mut mounts = self.mounts.lock();
+        let events = mounts
+            .iter()
+            .filter(|m| m.is_removable() && !new.contains(m))
+            .map(|m| MountEvent::Unmounted(m.clone()))
+            .chain(
+                new.iter()
+                    .filter(|m| m.is_removable() && !mounts.contains(m))
+                    .map(|m| MountEvent::Mounted(m.clone())),
+            )
+            .collect();
+        *mounts = new;
+        Ok(events)
+    }
+
+    /// Block waiting for the mount table changes
+    ///
+    /// The kernel signals them as an exceptional condition on the open mountinfo
+    pub fn watch_mounts(&self, on_event: impl Fn(&MountEvent)) -> Result<()> {
+        let file = File::open(&self.mountinfo)?;
+        loop {
+            let mut fds = [PollFd::new(file.as_fd(), PollFlags::POLLPRI)];
+            poll(&mut fds, PollTimeout::NONE)?;
+            for event in self.refresh_mounts()? {
+                on_event(&event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTINFO: &str = "\
+22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+23 22 0:21 / /proc rw,nosuid,nodev,noexec,relatime shared:5 - proc proc rw
+24 22 259:3 / /home rw,relatime shared:2 - ext4 /dev/nvme0n1p3 rw
+";
+
+    fn fake_mountinfo(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("sosd-{name}-{}", std::process::id()));
+        fs::write(&path, MOUNTINFO).unwrap();
+        path
+    }
+
+    fn usage(available: u64) -> DiskUsage {
+        DiskUsage {
+            mount_point: PathBuf::from("/"),
+            available,
+            total: 100,
+        }
+    }
+
+    #[test]
+    fn test_parse_mountinfo() {
+        let mounts = parse_mountinfo(
+            "40 22 8:17 / /run/media/user/My\\040Disk rw,nosuid - vfat /dev/sdb1 rw\n\
+             41 22 0:50 / /mnt/nas rw master:3 shared:9 - nfs4 nas:/export rw\n\
+             truncated line",
+        );
+        assert_eq!(
+            mounts,
+            [
+                Mount {
+                    source: "/dev/sdb1".to_owned(),
+                    mount_point: PathBuf::from("/run/media/user/My Disk"),
+                    fs_type: "vfat".to_owned(),
+                },
+                Mount {
+                    source: "nas:/export".to_owned(),
+                    mount_point: PathBuf::from("/mnt/nas"),
+                    fs_type: "nfs4".to_owned(),
+                },
+            ]
+        );
+        assert_eq!(mounts[0].name(), "My Disk");
+        assert!(mounts[0].is_removable());
+    }
+
+    #[test]
+    fn test_mount_events() {
+        let path = fake_mountinfo("mountinfo");
+        let manager = StorageManager::new(&path, vec![], vec![]).unwrap();
+        assert!(manager.refresh_mounts().unwrap().is_empty());
+
+        let usb = "40 22 8:17 / /run/media/user/USB rw,nosuid - vfat /dev/sdb1 rw\n";
+        fs::write(&path, format!("{MOUNTINFO}{usb}")).unwrap();
+        let events = manager.refresh_mounts().unwrap();
+        assert_eq!(events.len(), 1);
+        let MountEvent::Mounted(mount) = &events[0] else {
+            panic!("expected a mount, got {events:?}");
+        };
+        assert_eq!(mount.name(), "USB");
+        assert_eq!(mount.source(), "/dev/sdb1");
+
+        // Only the removable media is reported
+        fs::write(
+            &path,
+            MOUNTINFO.replace("24 22", "25 22").replace("/home", "/srv"),
+        )
+        .unwrap();
+        assert_eq!(
+            manager.refresh_mounts().unwrap(),
+            [MountEvent::Unmounted(mount.clone())]
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_space_thresholds() {
+        let mut alerts = SpaceAlerts::new(vec![5, 10]);
+        assert_eq!(alerts.update(&usage(50)), None);
+        assert_eq!(alerts.update(&usage(9)), Some(10));
+        assert_eq!(alerts.update(&usage(8)), None);
+        assert_eq!(alerts.update(&usage(4)), Some(5));
+        assert_eq!(alerts.update(&usage(3)), None);
+        // Freeing some space rearms the lower threshold
+        assert_eq!(alerts.update(&usage(7)), None);
+        assert_eq!(alerts.update(&usage(5)), Some(5));
+        assert_eq!(alerts.update(&usage(30)), None);
+        assert_eq!(alerts.update(&usage(10)), Some(10));
+    }
+
+    #[test]
+    fn test_usage_same_filesystem() {
+        let path = fake_mountinfo("mountinfo-usage");
+        let root = std::env::temp_dir();
+        let manager =
+            StorageManager::new(&path, vec![root.clone(), root.join(".")], vec![100]).unwrap();
+        let alerts = manager.refresh_usage();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].0, 100);
+        assert!(alerts[0].1.total() > 0);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,42 @@
+//! Private bus for the tests of the dbus services
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+
+use zbus::connection::Builder;
+
+pub struct PrivateBus {
+    daemon: Child,
+    address: String,
+}
+
+impl PrivateBus {
+    /// Start a dbus-daemon, `None` if it is not installed
+    pub fn new() -> Option<Self> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take()?)
+            .read_line(&mut address)
+            .ok()?;
+
+        Some(Self {
+            daemon,
+            address: address.trim().to_owned(),
+        })
+    }
+
+    pub fn builder(&self) -> Builder<'static> {
+        Builder::address(self.address.as_str()).unwrap()
+    }
+}
+
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        _ = self.daemon.kill();
+    }
+}
@@ -1,24 +1,28 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashSet, VecDeque};
 use std::sync::RwLock;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use ::services::{Icon, ServiceBroadcast};
-use config::{Config, InputAction, InputModifier, NotificationAction, Urgency, UrgencyItemConfig};
+use ::services::{Icon, ServiceBroadcast, Timeout};
+use config::{Config, InputAction, Urgency, UrgencyItemConfig};
 use cosmic_text::{Attrs, Buffer, FontSystem, Metrics, SwashCache};
 use raqote::*;
-use winit::dpi::LogicalPosition;
-use winit::event::{ButtonSource, FingerId, Modifiers, MouseScrollDelta, WindowEvent};
-use winit::keyboard::ModifiersKeyState;
+use winit::event::{
+    ButtonSource, FingerId, Modifiers, MouseScrollDelta, PointerSource, WindowEvent,
+};
 
 mod event_loop;
+mod gesture;
+mod group;
+mod input;
 mod services;
+mod stack;
 
-use crate::components::{Background, Component, IconComponent, Slider, Text};
-use crate::utils::ToColor;
+use crate::components::{Background, Badge, Component, IconComponent, Slider, Text};
 
 use self::event_loop::{ContentState, WindowState};
-
-type Touch = (Option<LogicalPosition<f32>>, Option<LogicalPosition<f32>>);
+use self::gesture::GestureRecognizer;
+use self::group::NotificationGroup;
+use self::stack::StackEntry;
 
 pub trait App: From<Config> + Sized + Sync + Send {
     fn show(&self) -> bool;
@@ -26,17 +30,36 @@ pub trait App: From<Config> + Sized + Sync + Send {
     fn update(&mut self, _: AppMessage) {}
     fn get_output(&self) -> Option<String>;
     fn draw(&mut self, ctx: &mut DrawTarget);
+    /// Messages shown at once, the surface grows to fit them
+    fn slots(&self) -> u32 {
+        1
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AppMessage {
     Close,
     Slider {
         id: Option<u32>,
         urgency: Urgency,
         icon: Option<Icon>,
-        timeout: Option<i32>,
+        timeout: Option<Timeout>,
+        value: f32,
+        /// Text next to the slider
+        label: Option<String>,
+        bg: Option<String>,
+        fg: Option<String>,
+        output: Option<String>,
+    },
+    /// Slider updated in place while its id is shown, for playback or download progress
+    LiveSlider {
+        id: u32,
+        urgency: Urgency,
+        icon: Option<Icon>,
+        timeout: Option<Timeout>,
         value: f32,
+        /// Text next to the slider, like the elapsed and total time
+        label: Option<String>,
         bg: Option<String>,
         fg: Option<String>,
         output: Option<String>,
@@ -46,12 +69,26 @@ pub enum AppMessage {
         title: String,
         urgency: Urgency,
         icon: Option<Icon>,
-        timeout: Option<i32>,
+        timeout: Option<Timeout>,
         body: Option<String>,
         bg: Option<String>,
         fg: Option<String>,
         output: Option<String>,
     },
+    /// Notification without timeout, shown again after the other messages until it is unpinned
+    Pin {
+        id: u32,
+        title: String,
+        urgency: Urgency,
+        icon: Option<Icon>,
+        body: Option<String>,
+        bg: Option<String>,
+        fg: Option<String>,
+        output: Option<String>,
+    },
+    Unpin {
+        id: u32,
+    },
 }
 
 pub struct MainApp {
@@ -60,12 +97,31 @@ pub struct MainApp {
     modifiers: Modifiers,
     current_id: Option<u32>,
     output: Option<String>,
-    touches: HashMap<FingerId, Touch>,
+    /// Last `AppMessage::Pin`, it comes back when the window would hide
+    pinned: Option<AppMessage>,
+    /// Notifications that never expire, they come back after the messages that replace them
+    kept: Vec<AppMessage>,
+    /// Ids of the resident notifications, they stay when one of their actions is invoked
+    resident: HashSet<u32>,
+    /// Notifications of the application shown, collapsed into one OSD
+    group: Option<NotificationGroup>,
+    /// Older messages still visible with the stack layout, the most recent first
+    stack: Vec<StackEntry>,
+    /// Last notifications with an id, the oldest first
+    history: VecDeque<AppMessage>,
+    /// Notifications hidden by `Snooze`, or the closed pinned message, and when they come back
+    snoozed: Vec<(Instant, AppMessage)>,
+    gestures: GestureRecognizer<FingerId>,
+    /// Sideways offset of the OSD following the finger that swipes it away
+    drag_offset: f32,
+    /// Since when the pointer is over the OSD, the timeouts are paused meanwhile
+    hovered_at: Option<Instant>,
 
     fonts: FontSystem,
     sw_cache: SwashCache,
     title_text: Buffer,
     description_text: Buffer,
+    badge_text: Buffer,
     icon_char: Buffer,
 
     // Components
@@ -74,6 +130,7 @@ pub struct MainApp {
     slider: Option<Slider>,
     title: Option<Text>,
     description: Option<Text>,
+    badge: Option<Badge>,
 
     // Layout properties
     radius: f32,
@@ -89,6 +146,13 @@ pub struct MainApp {
 
 pub static ICON_SIZE: RwLock<f32> = RwLock::new(12.0);
 
+/// Pixels the text scrolls with each line of the mouse wheel
+const SCRUB_STEP: f32 = 30.0;
+
+/// A closed pinned message comes back after this while it is still pinned, like the capture
+/// indicator while the microphone is in use
+const REPIN_AFTER: Duration = Duration::from_secs(60);
+
 impl From<Config> for MainApp {
     fn from(config: Config) -> Self {
         let show_duration = config.globals.show_duration.unwrap_or(5.0);
@@ -103,6 +167,7 @@ impl From<Config> for MainApp {
         let metrics = Metrics::new(size, size);
         let title_text = Buffer::new(&mut fonts, metrics);
         let description_text = Buffer::new(&mut fonts, metrics.scale(0.85));
+        let badge_text = Buffer::new(&mut fonts, metrics.scale(0.7));
         let icon_char = Buffer::new(&mut fonts, metrics);
 
         let background = Background::new(&config, (None, None), ());
@@ -111,14 +176,24 @@ impl From<Config> for MainApp {
             broadcast: None,
             notified_levels: HashSet::new(),
             modifiers: Modifiers::default(),
-            touches: HashMap::new(),
+            gestures: GestureRecognizer::default(),
+            drag_offset: 0.0,
+            hovered_at: None,
             current_id: None,
             output: config.output.clone(),
+            pinned: None,
+            kept: Vec::new(),
+            resident: HashSet::new(),
+            group: None,
+            stack: Vec::new(),
+            history: VecDeque::new(),
+            snoozed: Vec::new(),
 
             fonts,
             icon_char,
             title_text,
             description_text,
+            badge_text,
             sw_cache: SwashCache::new(),
 
             config,
@@ -131,6 +206,7 @@ impl From<Config> for MainApp {
             title: None,
             slider: None,
             description: None,
+            badge: None,
 
             content_state: ContentState::Idle,
             window_state: WindowState::Hidden,
@@ -143,6 +219,11 @@ impl App for MainApp {
     fn show(&self) -> bool {
         !matches!(self.window_state, WindowState::Hidden)
             || !matches!(self.content_state, ContentState::Idle)
+            || !self.stack.is_empty()
+    }
+
+    fn slots(&self) -> u32 {
+        self.used_slots()
     }
 
     fn get_output(&self) -> Option<String> {
@@ -150,20 +231,8 @@ impl App for MainApp {
     }
 
     fn event(&mut self, event: &WindowEvent) {
-        let Some(actions) = self.config.actions.clone() else {
-            return;
-        };
-        let curr_id = self.current_id;
-        let modifiers = if self.modifiers.lalt_state() == ModifiersKeyState::Pressed {
-            Some(InputModifier::Alt)
-        } else if self.modifiers.lcontrol_state() == ModifiersKeyState::Pressed {
-            Some(InputModifier::Ctrl)
-        } else if self.modifiers.lshift_state() == ModifiersKeyState::Pressed {
-            Some(InputModifier::Shift)
-        } else {
-            None
-        };
         match event {
+            WindowEvent::KeyboardInput { event, .. } => self.key_input(event),
             WindowEvent::PointerButton {
                 state,
                 position,
@@ -171,9 +240,11 @@ impl App for MainApp {
                 ..
             } => {
                 let position: winit::dpi::LogicalPosition<f32> = position.to_logical(1.0);
+                let now = Instant::now();
                 if *state == winit::event::ElementState::Pressed {
                     if let ButtonSource::Touch { finger_id, .. } = button {
-                        self.touches.insert(*finger_id, (Some(position), None));
+                        self.gestures
+                            .down(*finger_id, (position.x, position.y), now);
                     }
                     let input_action = match button {
                         ButtonSource::Mouse(button) => match button {
@@ -186,108 +257,47 @@ impl App for MainApp {
                             return;
                         }
                     };
-                    if let Some(input_event) = actions.get(&input_action) {
-                        if input_event
-                            .modifier
-                            .zip(modifiers)
-                            .is_some_and(|(m, em)| m == em)
-                            || input_event.modifier.is_none()
-                        {
-                            match input_event.action {
-                                NotificationAction::Close => {
-                                    self.update(AppMessage::Close);
-                                }
-                                NotificationAction::OpenNotification => {
-                                    if let (Some(broadcast), Some(curr_id)) =
-                                        (self.broadcast.clone(), curr_id)
-                                    {
-                                        tokio::spawn(async move {
-                                            broadcast
-                                                .notify_action::<Self>(curr_id, "default")
-                                                .await;
-                                        });
-                                        self.update(AppMessage::Close)
-                                    }
-                                }
-                            }
-                        }
-                    }
+                    self.dispatch(input_action, Some(position.y));
                 }
                 if *state == winit::event::ElementState::Released {
                     let ButtonSource::Touch { finger_id, .. } = button else {
                         return;
                     };
-                    let Some((Some(start), _end)) = self.touches.get(finger_id) else {
-                        return;
-                    };
-                    let delta_x = (start.x - position.x).abs();
-                    let delta_y = start.y - position.y;
-                    let input_action = if delta_y > 50.0 && delta_x < 30.0 {
-                        InputAction::TouchSwipeUp
-                    } else if delta_y < 50.0 && delta_x < 30.0 {
-                        InputAction::TouchSwipeDown
-                    } else {
-                        return;
-                    };
-
-                    if let Some(input_event) = actions.get(&input_action) {
-                        if input_event
-                            .modifier
-                            .zip(modifiers)
-                            .is_some_and(|(m, em)| m == em)
-                            || input_event.modifier.is_none()
-                        {
-                            match input_event.action {
-                                NotificationAction::Close => self.update(AppMessage::Close),
-                                NotificationAction::OpenNotification => {
-                                    if let (Some(broadcast), Some(curr_id)) =
-                                        (self.broadcast.clone(), curr_id)
-                                    {
-                                        tokio::spawn(async move {
-                                            broadcast
-                                                .notify_action::<Self>(curr_id, "default")
-                                                .await;
-                                        });
-                                        self.update(AppMessage::Close)
-                                    }
-                                }
-                            }
-                        }
+                    if let Some((input_action, (_, y))) =
+                        self.gestures.up(finger_id, (position.x, position.y), now)
+                    {
+                        self.dispatch(input_action, Some(y));
                     }
                 }
             }
+            WindowEvent::PointerMoved {
+                position,
+                source: PointerSource::Touch { finger_id, .. },
+                ..
+            } => {
+                let position: winit::dpi::LogicalPosition<f32> = position.to_logical(1.0);
+                self.gestures
+                    .moved(finger_id, (position.x, position.y), Instant::now());
+            }
             WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+            WindowEvent::PointerEntered { .. } => {
+                self.hovered_at.get_or_insert_with(Instant::now);
+            }
+            WindowEvent::PointerLeft { .. } => self.resume_timers(Instant::now()),
             WindowEvent::MouseWheel { delta, .. } => {
                 let MouseScrollDelta::LineDelta(_, y) = delta else {
                     return;
                 };
+                // The text too long to fit is scrolled instead
+                if self.scrub_text(-y * SCRUB_STEP) {
+                    return;
+                }
                 let input_action = if *y > 0.0 {
                     InputAction::ScrollUp
                 } else {
                     InputAction::ScrollDown
                 };
-                if let Some(input_event) = actions.get(&input_action) {
-                    if input_event
-                        .modifier
-                        .zip(modifiers)
-                        .is_some_and(|(m, em)| m == em)
-                        || input_event.modifier.is_none()
-                    {
-                        match input_event.action {
-                            NotificationAction::Close => self.update(AppMessage::Close),
-                            NotificationAction::OpenNotification => {
-                                if let (Some(broadcast), Some(curr_id)) =
-                                    (self.broadcast.clone(), curr_id)
-                                {
-                                    tokio::spawn(async move {
-                                        broadcast.notify_action::<Self>(curr_id, "default").await;
-                                    });
-                                    self.update(AppMessage::Close)
-                                }
-                            }
-                        }
-                    }
-                }
+                self.dispatch(input_action, None);
             }
             _ => {}
         };
@@ -298,6 +308,150 @@ impl App for MainApp {
         let current_time = Instant::now();
         let window = self.config.window.clone().unwrap_or_default();
 
+        let msg = match msg {
+            AppMessage::Pin { id, .. } => {
+                self.forget_closed_pin(id);
+                let shown = self.current_id == Some(id) || !self.show();
+                self.pinned = Some(msg.clone());
+                // Over another message it waits until that one finishes
+                if !shown {
+                    return;
+                }
+                msg
+            }
+            AppMessage::Unpin { id } => {
+                self.forget_closed_pin(id);
+                if !self.pinned_id().is_some_and(|pinned| pinned == id) {
+                    return;
+                }
+                self.pinned = None;
+                if self.current_id != Some(id) {
+                    return;
+                }
+                AppMessage::Close
+            }
+            // Closing the pinned message hides it for a while, it comes back if it is still pinned
+            AppMessage::Close => {
+                if self.current_id.is_some() && self.current_id == self.pinned_id() {
+                    if let Some(pinned) = self.pinned.take() {
+                        self.snoozed.push((current_time + REPIN_AFTER, pinned));
+                    }
+                }
+                msg
+            }
+            msg => msg,
+        };
+        self.keep_unexpiring(&msg);
+
+        // A live slider already shown only moves its value, without the enter animation
+        //
+        // Adding or removing the icon moves the slider, so it is laid out again
+        let msg = match msg {
+            AppMessage::LiveSlider {
+                id,
+                urgency,
+                icon,
+                value,
+                label,
+                timeout,
+                bg,
+                fg,
+                ..
+            } if self.current_id == Some(id)
+                && self.slider.is_some()
+                && icon.is_some() == self.icon.is_some()
+                && matches!(
+                    self.window_state,
+                    WindowState::Entering { .. } | WindowState::Showing { .. }
+                ) =>
+            {
+                let urgency = UrgencyItemConfig::from((&self.config, urgency));
+                let (fg, bg) = self.resolve_colors(fg, bg, &urgency);
+                self.background.change_color(bg);
+                if let Some(slider) = self.slider.as_mut() {
+                    slider.animate_value(value);
+                    slider.change_color(bg, fg);
+                }
+                if let Some(i) = icon {
+                    self.icon.replace(IconComponent::new(
+                        &self.config,
+                        (
+                            Some(self.safe_left),
+                            Some(self.half_y - (self.icon_char.metrics().font_size / 1.5)),
+                        ),
+                        (fg, i),
+                    ));
+                }
+                match label {
+                    Some(label) => self.set_slider_label(&label, fg),
+                    None => {
+                        self.description = None;
+                        if let Some(slider) = self.slider.as_mut() {
+                            slider.reserve_end(0.0);
+                        }
+                    }
+                }
+                if timeout.is_some() {
+                    self.show_duration = self.message_duration(timeout, &urgency);
+                }
+                if let WindowState::Showing { .. } = self.window_state {
+                    self.window_state = WindowState::Showing {
+                        start_time: current_time,
+                    };
+                }
+                return;
+            }
+            AppMessage::LiveSlider {
+                id,
+                urgency,
+                icon,
+                timeout,
+                value,
+                label,
+                bg,
+                fg,
+                output,
+            } => AppMessage::Slider {
+                id: Some(id),
+                urgency,
+                icon,
+                timeout,
+                value,
+                label,
+                bg,
+                fg,
+                output,
+            },
+            AppMessage::Pin {
+                id,
+                title,
+                urgency,
+                icon,
+                body,
+                bg,
+                fg,
+                output,
+            } => AppMessage::Notification {
+                id: Some(id),
+                title,
+                urgency,
+                icon,
+                timeout: None,
+                body,
+                bg,
+                fg,
+                output,
+            },
+            msg => msg,
+        };
+
+        self.remember(&msg);
+
+        // With the stack layout the shown message moves down, making room for the new one
+        if self.should_stack(&msg) {
+            self.push_to_stack(current_time);
+        }
+
         // Manejar estados de animación
         match self.window_state {
             WindowState::Hidden => {
@@ -338,34 +492,20 @@ impl App for MainApp {
                 icon,
                 timeout,
                 value,
+                label,
                 bg,
                 fg,
                 output,
             } => {
                 self.clear_content();
                 self.current_id = id;
-                self.output = output;
+                self.output = output.or_else(|| self.config.output.clone());
 
                 let mut mult = 3.65;
                 let urgency = UrgencyItemConfig::from((&self.config, urgency));
-                self.show_duration = timeout
-                    .map(|t| t as f32)
-                    .or(urgency.show_duration)
-                    .or(self.config.globals.show_duration)
-                    .unwrap_or(5.0);
-
-                let fg = fg
-                    .or(urgency.foreground_color.clone())
-                    .or(self.config.globals.foreground_color.clone())
-                    .as_deref()
-                    .map(ToColor::to_color)
-                    .unwrap();
-                let bg = bg
-                    .or(urgency.background.clone())
-                    .or(self.config.globals.background.clone())
-                    .as_deref()
-                    .map(ToColor::to_color)
-                    .unwrap();
+                self.show_duration = self.message_duration(timeout, &urgency);
+
+                let (fg, bg) = self.resolve_colors(fg, bg, &urgency);
                 self.background.change_color(bg);
 
                 // Actualizar componentes
@@ -396,6 +536,10 @@ impl App for MainApp {
                     slider.change_color(bg, fg);
                     self.slider.replace(slider);
                 }
+
+                if let Some(label) = label {
+                    self.set_slider_label(&label, fg);
+                }
             }
 
             AppMessage::Notification {
@@ -411,31 +555,16 @@ impl App for MainApp {
             } => {
                 self.clear_content();
                 self.current_id = id;
-                self.output = output;
+                self.output = output.or_else(|| self.config.output.clone());
 
                 let urgency = UrgencyItemConfig::from((&self.config, urgency));
                 println!(
                     "Urgency: {urgency:?} - Global: {:?} - Global BG: {:?}",
                     self.config.globals.foreground_color, self.config.globals.background
                 );
-                self.show_duration = timeout
-                    .map(|t| t as f32)
-                    .or(urgency.show_duration)
-                    .or(self.config.globals.show_duration)
-                    .unwrap_or(5.0);
-
-                let fg = fg
-                    .or(urgency.foreground_color.clone())
-                    .or(self.config.globals.foreground_color.clone())
-                    .as_deref()
-                    .map(ToColor::to_color)
-                    .unwrap();
-                let bg = bg
-                    .or(urgency.background.clone())
-                    .or(self.config.globals.background.clone())
-                    .as_deref()
-                    .map(ToColor::to_color)
-                    .unwrap();
+                self.show_duration = self.message_duration(timeout, &urgency);
+
+                let (fg, bg) = self.resolve_colors(fg, bg, &urgency);
                 self.background.change_color(bg);
 
                 let mut has_desc = false;
@@ -483,23 +612,55 @@ impl App for MainApp {
                     Attrs::new(),
                     cosmic_text::Shaping::Advanced,
                 );
-                self.title.replace(Text::new(
+                let title_y = if has_desc {
+                    window.height.unwrap_or(80) as f32 * 0.3
+                } else {
+                    self.half_y - (font_size / 2.0)
+                };
+                let mut title = Text::new(
                     &self.config,
-                    (
-                        Some(safe_left),
-                        Some(if has_desc {
-                            window.height.unwrap_or(80) as f32 * 0.3
-                        } else {
-                            self.half_y - (font_size / 2.0)
-                        }),
-                    ),
+                    (Some(safe_left), Some(title_y)),
                     (
                         self.title_text.metrics().font_size,
                         self.title_text.layout_runs().map(|l| l.line_w).sum(),
                         max_size_text,
                         fg,
                     ),
-                ));
+                );
+
+                // The count of the group goes at the end of the title, inverting the colors
+                if let Some(badge) = self
+                    .group
+                    .as_ref()
+                    .filter(|g| g.contains(id))
+                    .and_then(NotificationGroup::badge)
+                {
+                    self.badge_text.set_text(
+                        &mut self.fonts,
+                        &badge,
+                        Attrs::new(),
+                        cosmic_text::Shaping::Advanced,
+                    );
+                    let mut badge = Badge::new(
+                        &self.config,
+                        (None, Some(title_y)),
+                        (
+                            self.badge_text.metrics().font_size,
+                            self.badge_text.layout_runs().map(|l| l.line_w).sum(),
+                            fg,
+                            bg,
+                        ),
+                    );
+                    badge.end_at(title.end());
+                    title.reserve_end(badge.width() + self.radius * 0.1);
+                    self.badge.replace(badge);
+                }
+                self.title.replace(title);
+            }
+
+            AppMessage::LiveSlider { .. } => unreachable!("converted to a slider above"),
+            AppMessage::Pin { .. } | AppMessage::Unpin { .. } => {
+                unreachable!("converted to a notification or a close above")
             }
 
             AppMessage::Close => {
@@ -517,6 +678,12 @@ impl App for MainApp {
     }
 
     fn draw(&mut self, ctx: &mut DrawTarget) {
+        self.wake_snoozed(Instant::now());
+        if let Some((input_action, (_, y))) = self.gestures.long_press(Instant::now()) {
+            self.dispatch(input_action, Some(y));
+        }
+        self.draw_stack(ctx);
+
         if matches!(self.window_state, WindowState::Hidden)
             && matches!(self.content_state, ContentState::Idle)
         {
@@ -534,10 +701,23 @@ impl App for MainApp {
             || matches!(self.content_state, ContentState::Idle)
         {
             self.reset();
+            if let Some(msg) = self.next_kept().or_else(|| self.pinned.clone()) {
+                self.update(msg);
+            }
             return;
         }
 
+        // The OSD follows the finger swiping it away, and leaves from there
+        let drag = self.gestures.drag();
+        if drag != 0.0 || !matches!(self.window_state, WindowState::Exiting { .. }) {
+            self.drag_offset = drag;
+        }
+
         // Dibujar componentes
+        let transform = self
+            .slot_transform(ctx, 0.0)
+            .then(&Transform::translation(self.drag_offset, 0.0));
+        ctx.set_transform(&transform);
         self.background.draw(ctx, window_progress, ());
 
         if let Some(slider) = self.slider.as_mut() {
@@ -550,7 +730,9 @@ impl App for MainApp {
                 (&mut self.fonts, &mut self.sw_cache, &mut self.icon_char),
             );
         }
+        let hovered = self.hovered_at.is_some();
         if let Some(title) = self.title.as_mut() {
+            title.pause(hovered);
             title.draw(
                 ctx,
                 content_progress,
@@ -558,11 +740,20 @@ impl App for MainApp {
             );
         }
         if let Some(description) = self.description.as_mut() {
+            description.pause(hovered);
             description.draw(
                 ctx,
                 content_progress,
                 (&mut self.fonts, &mut self.sw_cache, &self.description_text),
             );
         }
+        if let Some(badge) = self.badge.as_mut() {
+            badge.draw(
+                ctx,
+                content_progress,
+                (&mut self.fonts, &mut self.sw_cache, &self.badge_text),
+            );
+        }
+        ctx.set_transform(&Transform::identity());
     }
 }
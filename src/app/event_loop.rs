@@ -1,8 +1,15 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use crate::utils::ease_out_cubic;
+use config::UrgencyItemConfig;
+use cosmic_text::Attrs;
+use raqote::SolidSource;
+use services::{CloseReason, Timeout};
 
-use super::MainApp;
+use crate::components::{Component, Text};
+use crate::utils::{ease_out_cubic, ToColor};
+
+use super::input::id_of;
+use super::{AppMessage, MainApp};
 
 pub(super) enum ContentState {
     Idle,
@@ -24,15 +31,186 @@ impl MainApp {
         self.title = None;
         self.slider = None;
         self.description = None;
+        self.badge = None;
+    }
+
+    /// Show the label at the end of the slider, shrinking it to make room
+    pub(super) fn set_slider_label(&mut self, label: &str, fg: SolidSource) {
+        let Some(slider) = self.slider.as_mut() else {
+            return;
+        };
+        let width = self
+            .config
+            .window
+            .clone()
+            .unwrap_or_default()
+            .width
+            .unwrap_or(600) as f32;
+
+        self.description_text.set_text(
+            &mut self.fonts,
+            label,
+            Attrs::new(),
+            cosmic_text::Shaping::Advanced,
+        );
+        let label_width: f32 = self.description_text.layout_runs().map(|l| l.line_w).sum();
+        slider.reserve_end(label_width + self.radius * 0.1);
+
+        self.description.replace(Text::new(
+            &self.config,
+            (Some(slider.end() - label_width), Some(self.half_y)),
+            (
+                self.description_text.metrics().font_size,
+                label_width,
+                (width - label_width) / self.radius,
+                fg,
+            ),
+        ));
+    }
+
+    /// Scroll the title or the description when they are too long to fit
+    pub(super) fn scrub_text(&mut self, delta: f32) -> bool {
+        let title = self.title.as_mut().is_some_and(|t| t.scrub(delta));
+        let description = self.description.as_mut().is_some_and(|d| d.scrub(delta));
+        title || description
+    }
+
+    /// Seconds to show a message
+    ///
+    /// The sticky urgencies never hide when the message has no timeout
+    pub(super) fn message_duration(
+        &self,
+        timeout: Option<Timeout>,
+        urgency: &UrgencyItemConfig,
+    ) -> f32 {
+        match timeout {
+            Some(Timeout::Never) => f32::INFINITY,
+            Some(Timeout::Seconds(t)) => t,
+            None if urgency.sticky.unwrap_or_default() => f32::INFINITY,
+            None => urgency
+                .show_duration
+                .or(self.config.globals.show_duration)
+                .unwrap_or(5.0),
+        }
+    }
+
+    /// Foreground and background of a message, from its own colors, then the urgency ones and
+    /// at last the global ones
+    pub(super) fn resolve_colors(
+        &self,
+        fg: Option<String>,
+        bg: Option<String>,
+        urgency: &UrgencyItemConfig,
+    ) -> (SolidSource, SolidSource) {
+        let fg = fg
+            .or(urgency.foreground_color.clone())
+            .or(self.config.globals.foreground_color.clone())
+            .as_deref()
+            .map(ToColor::to_color)
+            .unwrap();
+        let bg = bg
+            .or(urgency.background.clone())
+            .or(self.config.globals.background.clone())
+            .as_deref()
+            .map(ToColor::to_color)
+            .unwrap();
+        (fg, bg)
+    }
+
+    /// Id of the pinned message, it is never hidden by the show timer
+    pub(super) fn pinned_id(&self) -> Option<u32> {
+        match self.pinned {
+            Some(AppMessage::Pin { id, .. }) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Drop the closed pinned message waiting to come back, it was pinned again or unpinned
+    pub(super) fn forget_closed_pin(&mut self, id: u32) {
+        self.snoozed
+            .retain(|(_, msg)| !matches!(msg, AppMessage::Pin { id: pinned, .. } if *pinned == id));
+    }
+
+    /// Keep the notification that never expires, or forget it when its id is replaced by a
+    /// message that expires
+    pub(super) fn keep_unexpiring(&mut self, msg: &AppMessage) {
+        let id = match msg {
+            AppMessage::Notification { id: Some(id), .. }
+            | AppMessage::Slider { id: Some(id), .. }
+            | AppMessage::LiveSlider { id, .. } => *id,
+            _ => return,
+        };
+        self.kept.retain(|kept| id_of(kept) != Some(id));
+
+        let AppMessage::Notification {
+            timeout, urgency, ..
+        } = msg
+        else {
+            return;
+        };
+        let urgency = UrgencyItemConfig::from((&self.config, urgency.clone()));
+        if self.message_duration(*timeout, &urgency).is_infinite() {
+            self.kept.push(msg.clone());
+        }
+    }
+
+    /// Stop keeping the notification, its sender is told when it was closed with a reason
+    pub(super) fn forget_kept(&mut self, id: u32, reason: Option<CloseReason>) {
+        let kept = self.kept.len();
+        self.kept.retain(|msg| id_of(msg) != Some(id));
+        let Some(reason) = reason else {
+            return;
+        };
+        self.resident.remove(&id);
+        if self.kept.len() == kept {
+            return;
+        }
+        if let Some(broadcast) = self.broadcast.clone() {
+            tokio::spawn(async move {
+                broadcast.notify_closed::<Self>(id, reason).await;
+            });
+        }
+    }
+
+    /// Latest kept notification that is not on screen, to show when the window would hide
+    pub(super) fn next_kept(&self) -> Option<AppMessage> {
+        self.kept
+            .iter()
+            .rev()
+            .find(|msg| id_of(msg).is_some_and(|id| !self.is_stacked(id)))
+            .cloned()
     }
 
     pub(super) fn reset(&mut self) {
         self.clear_content();
+        self.group = None;
         self.content_state = ContentState::Idle;
         self.window_state = WindowState::Hidden;
+        self.hovered_at = None;
         self.show_duration = self.config.globals.show_duration.unwrap_or(5.0);
     }
 
+    /// Resume the timers paused while the pointer was over the OSD, leaving at least the grace
+    /// period before it hides
+    pub(super) fn resume_timers(&mut self, now: Instant) {
+        let Some(hovered_at) = self.hovered_at.take() else {
+            return;
+        };
+        self.delay_stack(now.saturating_duration_since(hovered_at));
+
+        let WindowState::Showing { start_time } = self.window_state else {
+            return;
+        };
+        let mut shown = hovered_at.saturating_duration_since(start_time);
+        let grace = self.config.globals.hover_grace.unwrap_or(1.0).max(0.0);
+        if self.show_duration.is_finite() && self.show_duration - shown.as_secs_f32() < grace {
+            shown = Duration::from_secs_f32((self.show_duration - grace).max(0.0));
+        }
+        self.window_state = WindowState::Showing {
+            start_time: now.checked_sub(shown).unwrap_or(now),
+        };
+    }
+
     pub(super) fn update_animation_states(&mut self, current_time: Instant) {
         let animation_duration = self.config.globals.animation_duration.unwrap_or(1.0);
         let show_duration = self.show_duration;
@@ -56,8 +234,14 @@ impl MainApp {
             }
 
             WindowState::Showing { start_time } => {
-                let elapsed = current_time.duration_since(*start_time).as_secs_f32();
-                if elapsed >= show_duration {
+                // The time stops while the pointer is over the OSD
+                let elapsed = self
+                    .hovered_at
+                    .unwrap_or(current_time)
+                    .saturating_duration_since(*start_time)
+                    .as_secs_f32();
+                let pinned = self.current_id.is_some() && self.current_id == self.pinned_id();
+                if elapsed >= show_duration && !pinned {
                     // Sincronizar la salida del contenido con la ventana
                     self.content_state = ContentState::Exiting {
                         start_time: current_time,
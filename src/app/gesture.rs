@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Instant;
+
+use config::InputAction;
+
+/// Farthest a tap or a long press can move
+const TAP_SLOP: f32 = 10.0;
+/// Shortest swipe, unless it is fast enough to be a fling
+const SWIPE_DISTANCE: f32 = 50.0;
+/// Speed of a fling, in pixels per second
+const FLING_VELOCITY: f32 = 500.0;
+/// How long a finger must stay down for a long press, in seconds
+const LONG_PRESS: f32 = 0.5;
+
+type Position = (f32, f32);
+
+struct Touch {
+    start: Position,
+    last: Position,
+    last_time: Instant,
+    /// Last speed of the finger, in pixels per second
+    velocity: Position,
+}
+
+impl Touch {
+    fn delta(&self) -> Position {
+        (self.last.0 - self.start.0, self.last.1 - self.start.1)
+    }
+}
+
+/// Turns the touches of the fingers into the touch `InputAction`s
+pub(super) struct GestureRecognizer<K> {
+    touches: HashMap<K, Touch>,
+    started_at: Option<Instant>,
+    /// Most fingers down at once since the gesture started
+    fingers: usize,
+    /// The long press already fired, lifting the finger does nothing else
+    long_pressed: bool,
+}
+
+impl<K: Eq + Hash> Default for GestureRecognizer<K> {
+    fn default() -> Self {
+        Self {
+            touches: HashMap::new(),
+            started_at: None,
+            fingers: 0,
+            long_pressed: false,
+        }
+    }
+}
+
+impl<K: Eq + Hash> GestureRecognizer<K> {
+    pub fn down(&mut self, finger: K, position: Position, now: Instant) {
+        if self.touches.is_empty() {
+            self.started_at = Some(now);
+            self.fingers = 0;
+            self.long_pressed = false;
+        }
+        self.touches.insert(
+            finger,
+            Touch {
+                start: position,
+                last: position,
+                last_time: now,
+                velocity: (0.0, 0.0),
+            },
+        );
+        self.fingers = self.fingers.max(self.touches.len());
+    }
+
+    pub fn moved(&mut self, finger: &K, position: Position, now: Instant) {
+        let Some(touch) = self.touches.get_mut(finger) else {
+            return;
+        };
+        let elapsed = now.duration_since(touch.last_time).as_secs_f32();
+        if elapsed > 0.0 {
+            touch.velocity = (
+                (position.0 - touch.last.0) / elapsed,
+                (position.1 - touch.last.1) / elapsed,
+            );
+        }
+        touch.last = position;
+        touch.last_time = now;
+    }
+
+    /// Gesture made when the last finger is lifted, with where it started
+    pub fn up(
+        &mut self,
+        finger: &K,
+        position: Position,
+        now: Instant,
+    ) -> Option<(InputAction, Position)> {
+        self.moved(finger, position, now);
+        let touch = self.touches.remove(finger)?;
+        if !self.touches.is_empty() || self.long_pressed {
+            return None;
+        }
+        let duration = now.duration_since(self.started_at?).as_secs_f32();
+
+        let (dx, dy) = touch.delta();
+        let distance = dx.hypot(dy);
+        let (vx, vy) = touch.velocity;
+        let action = if distance < TAP_SLOP {
+            (duration < LONG_PRESS && self.fingers == 1).then_some(InputAction::TouchTap)?
+        } else if distance >= SWIPE_DISTANCE || vx.hypot(vy) >= FLING_VELOCITY {
+            let two = self.fingers > 1;
+            match (dx.abs() > dy.abs(), dx > 0.0, dy > 0.0) {
+                (true, true, _) if two => InputAction::TouchTwoFingerSwipeRight,
+                (true, false, _) if two => InputAction::TouchTwoFingerSwipeLeft,
+                (false, _, true) if two => InputAction::TouchTwoFingerSwipeDown,
+                (false, _, false) if two => InputAction::TouchTwoFingerSwipeUp,
+                (true, true, _) => InputAction::TouchSwipeRight,
+                (true, false, _) => InputAction::TouchSwipeLeft,
+                (false, _, true) => InputAction::TouchSwipeDown,
+                (false, _, false) => InputAction::TouchSwipeUp,
+            }
+        } else {
+            return None;
+        };
+        Some((action, touch.start))
+    }
+
+    /// The long press of a finger held still, once per gesture
+    pub fn long_press(&mut self, now: Instant) -> Option<(InputAction, Position)> {
+        let held = now.duration_since(self.started_at?).as_secs_f32();
+        if self.long_pressed || self.fingers != 1 || held < LONG_PRESS {
+            return None;
+        }
+        let touch = self.touches.values().next()?;
+        let (dx, dy) = touch.delta();
+        if dx.hypot(dy) >= TAP_SLOP {
+            return None;
+        }
+        self.long_pressed = true;
+        Some((InputAction::TouchLongPress, touch.start))
+    }
+
+    /// Horizontal move of the finger dragging the OSD sideways, so it follows the finger
+    pub fn drag(&self) -> f32 {
+        if self.fingers != 1 || self.long_pressed {
+            return 0.0;
+        }
+        match self.touches.values().next().map(Touch::delta) {
+            Some((dx, dy)) if dx.abs() > dy.abs() && dx.abs() >= TAP_SLOP => dx,
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn after(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_tap_and_long_press() {
+        let now = Instant::now();
+        let mut gestures = GestureRecognizer::default();
+
+        gestures.down(0, (100.0, 40.0), now);
+        gestures.moved(&0, (103.0, 42.0), after(now, 50));
+        assert_eq!(gestures.long_press(after(now, 100)), None);
+        assert_eq!(
+            gestures.up(&0, (103.0, 42.0), after(now, 120)),
+            Some((InputAction::TouchTap, (100.0, 40.0)))
+        );
+
+        gestures.down(0, (100.0, 40.0), after(now, 1000));
+        assert_eq!(
+            gestures.long_press(after(now, 1600)),
+            Some((InputAction::TouchLongPress, (100.0, 40.0)))
+        );
+        assert_eq!(gestures.long_press(after(now, 1700)), None);
+        assert_eq!(gestures.up(&0, (100.0, 40.0), after(now, 1800)), None);
+
+        // Held too long without the long press polled, it isn't a tap
+        gestures.down(0, (100.0, 40.0), after(now, 2000));
+        assert_eq!(gestures.up(&0, (100.0, 40.0), after(now, 2700)), None);
+    }
+
+    #[test]
+    fn test_swipes() {
+        let now = Instant::now();
+        let mut gestures = GestureRecognizer::default();
+
+        gestures.down(0, (100.0, 40.0), now);
+        gestures.moved(&0, (60.0, 45.0), after(now, 100));
+        assert_eq!(gestures.drag(), -40.0);
+        assert_eq!(
+            gestures.up(&0, (20.0, 45.0), after(now, 200)).map(|g| g.0),
+            Some(InputAction::TouchSwipeLeft)
+        );
+        assert_eq!(gestures.drag(), 0.0);
+
+        // Short but fast enough
+        gestures.down(0, (100.0, 60.0), now);
+        gestures.moved(&0, (100.0, 50.0), after(now, 10));
+        assert_eq!(
+            gestures.up(&0, (100.0, 40.0), after(now, 20)).map(|g| g.0),
+            Some(InputAction::TouchSwipeUp)
+        );
+
+        // Short and slow
+        gestures.down(0, (100.0, 40.0), now);
+        assert_eq!(gestures.up(&0, (100.0, 70.0), after(now, 500)), None);
+
+        // Two fingers, the gesture ends with the last one
+        gestures.down(0, (100.0, 10.0), now);
+        gestures.down(1, (150.0, 10.0), after(now, 20));
+        gestures.moved(&0, (100.0, 80.0), after(now, 100));
+        assert_eq!(gestures.drag(), 0.0);
+        assert_eq!(gestures.up(&0, (100.0, 90.0), after(now, 150)), None);
+        assert_eq!(
+            gestures.up(&1, (150.0, 90.0), after(now, 160)).map(|g| g.0),
+            Some(InputAction::TouchTwoFingerSwipeDown)
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use super::{App, AppMessage, MainApp};
+
+/// Notifications of the same application collapsed into one OSD
+pub(super) struct NotificationGroup {
+    app: String,
+    /// `AppMessage::Notification` from the oldest to the most recent
+    messages: Vec<AppMessage>,
+    /// Index of the message shown
+    selected: usize,
+}
+
+fn message_id(msg: &AppMessage) -> Option<u32> {
+    match msg {
+        AppMessage::Notification { id, .. } => *id,
+        _ => None,
+    }
+}
+
+impl NotificationGroup {
+    pub fn new(app: String) -> Self {
+        Self {
+            app,
+            messages: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn app(&self) -> &str {
+        &self.app
+    }
+
+    pub fn contains(&self, id: Option<u32>) -> bool {
+        id.is_some() && self.messages.iter().any(|m| message_id(m) == id)
+    }
+
+    /// Add the message as the most recent and show it, it replaces the one with the same id
+    pub fn push(&mut self, msg: AppMessage) {
+        let id = message_id(&msg);
+        self.messages
+            .retain(|m| id.is_none() || message_id(m) != id);
+        self.messages.push(msg);
+        self.selected = self.messages.len() - 1;
+    }
+
+    /// Remove the message, showing the most recent of the others if it was shown
+    pub fn remove(&mut self, id: u32) {
+        let Some(index) = self.messages.iter().position(|m| message_id(m) == Some(id)) else {
+            return;
+        };
+        self.messages.remove(index);
+        if index < self.selected {
+            self.selected -= 1;
+        } else if index == self.selected {
+            self.selected = self.messages.len().saturating_sub(1);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Text of the badge like `Slack · 5 new`, only when there is more than one message
+    pub fn badge(&self) -> Option<String> {
+        (self.messages.len() > 1).then(|| format!("{} · {} new", self.app, self.messages.len()))
+    }
+
+    pub fn selected(&self) -> Option<AppMessage> {
+        self.messages.get(self.selected).cloned()
+    }
+
+    /// Move through the messages going around at the ends, `1` is towards the most recent
+    pub fn cycle(&mut self, step: isize) -> Option<AppMessage> {
+        let len = self.messages.len() as isize;
+        if len < 2 {
+            return None;
+        }
+        self.selected = (self.selected as isize + step).rem_euclid(len) as usize;
+        self.selected()
+    }
+}
+
+impl MainApp {
+    /// Show the notification of `app`, collapsing it with the others of the same application
+    /// shown now
+    pub(super) fn show_grouped(&mut self, app: String, msg: AppMessage) {
+        let shown = self.show()
+            && self
+                .group
+                .as_ref()
+                .is_some_and(|g| g.app() == app && g.contains(self.current_id));
+        if !shown {
+            self.group = Some(NotificationGroup::new(app));
+        }
+        if let Some(group) = self.group.as_mut() {
+            group.push(msg.clone());
+        }
+        self.update(msg);
+    }
+
+    /// Show another notification of the group, `1` is the next one
+    pub(super) fn cycle_group(&mut self, step: isize) {
+        if !self
+            .group
+            .as_ref()
+            .is_some_and(|g| g.contains(self.current_id))
+        {
+            return;
+        }
+        if let Some(msg) = self.group.as_mut().and_then(|g| g.cycle(step)) {
+            self.update(msg);
+        }
+    }
+
+    /// Forget the closed notification, showing another of the group when it was shown
+    pub(super) fn close_grouped(&mut self, id: u32) {
+        let Some(group) = self.group.as_mut().filter(|g| g.contains(Some(id))) else {
+            return;
+        };
+        group.remove(id);
+        if self.current_id != Some(id) {
+            return;
+        }
+        match group.selected() {
+            Some(msg) => self.update(msg),
+            None => self.update(AppMessage::Close),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::Urgency;
+
+    use super::*;
+
+    fn notification(id: u32, title: &str) -> AppMessage {
+        AppMessage::Notification {
+            id: Some(id),
+            title: title.to_owned(),
+            urgency: Urgency::Normal,
+            icon: None,
+            timeout: None,
+            body: None,
+            bg: None,
+            fg: None,
+            output: None,
+        }
+    }
+
+    fn title(msg: Option<AppMessage>) -> String {
+        match msg {
+            Some(AppMessage::Notification { title, .. }) => title,
+            msg => panic!("expected a notification, got {msg:?}"),
+        }
+    }
+
+    #[test]
+    fn test_group_badge() {
+        let mut group = NotificationGroup::new("Slack".to_owned());
+        group.push(notification(1, "First"));
+        assert_eq!(group.badge(), None);
+
+        group.push(notification(2, "Second"));
+        group.push(notification(3, "Third"));
+        assert_eq!(group.badge().as_deref(), Some("Slack · 3 new"));
+        assert_eq!(title(group.selected()), "Third");
+
+        // Sending again with the same id replaces it
+        group.push(notification(2, "Edited"));
+        assert_eq!(group.badge().as_deref(), Some("Slack · 3 new"));
+        assert_eq!(title(group.selected()), "Edited");
+    }
+
+    #[test]
+    fn test_group_cycle() {
+        let mut group = NotificationGroup::new("Slack".to_owned());
+        assert!(group.cycle(1).is_none());
+        for id in 1..=3 {
+            group.push(notification(id, &id.to_string()));
+        }
+        assert_eq!(title(group.cycle(1)), "1");
+        assert_eq!(title(group.cycle(-1)), "3");
+        assert_eq!(title(group.cycle(-1)), "2");
+
+        group.remove(2);
+        assert_eq!(title(group.selected()), "3");
+
+        // Removing an older one keeps the shown one
+        group.push(notification(4, "4"));
+        assert_eq!(title(group.cycle(-1)), "3");
+        group.remove(1);
+        assert_eq!(title(group.selected()), "3");
+        group.remove(4);
+        group.remove(3);
+        assert!(group.is_empty());
+        assert!(group.selected().is_none());
+    }
+}
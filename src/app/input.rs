@@ -0,0 +1,279 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use config::{Action, InputAction, InputModifier, NotificationAction};
+use services::CloseReason;
+use winit::event::{ElementState, KeyEvent};
+use winit::keyboard::Key;
+
+use crate::buffer::is_wayland;
+
+use super::group::NotificationGroup;
+use super::{App, AppMessage, MainApp};
+
+/// Notifications kept for `ShowHistory`
+const HISTORY_SIZE: usize = 20;
+
+/// Action bound to the input, the binding without modifiers is used when none has the ones held
+/// down
+fn binding(
+    actions: &Action,
+    input: &InputAction,
+    pressed: &[InputModifier],
+) -> Option<NotificationAction> {
+    actions.find(input, pressed).map(|e| e.action.clone())
+}
+
+/// Name of the key in the bindings, `Escape` or `ArrowUp` for the named keys and the lowercase
+/// character for the others
+fn key_name(key: &Key) -> Option<String> {
+    match key {
+        Key::Named(key) => Some(format!("{key:?}")),
+        Key::Character(c) => Some(c.to_lowercase()),
+        _ => None,
+    }
+}
+
+pub(super) fn id_of(msg: &AppMessage) -> Option<u32> {
+    match msg {
+        AppMessage::Notification { id, .. } => *id,
+        _ => None,
+    }
+}
+
+/// Copy through `wl-copy` or `xclip`, the clipboard is owned by them after the OSD closes
+fn copy_to_clipboard(text: String) {
+    let mut command = match is_wayland() {
+        true => Command::new("wl-copy"),
+        false => {
+            let mut command = Command::new("xclip");
+            command.args(["-selection", "clipboard"]);
+            command
+        }
+    };
+    let Ok(mut child) = command.stdin(Stdio::piped()).spawn() else {
+        eprintln!("Cannot copy the notification, wl-copy or xclip is missing");
+        return;
+    };
+    std::thread::spawn(move || {
+        if let Some(mut stdin) = child.stdin.take() {
+            _ = stdin.write_all(text.as_bytes());
+        }
+        _ = child.wait();
+    });
+}
+
+impl MainApp {
+    /// Modifiers held down, with the left or the right key
+    fn pressed_modifiers(&self) -> Vec<InputModifier> {
+        let state = self.modifiers.state();
+        [
+            (state.shift_key(), InputModifier::Shift),
+            (state.control_key(), InputModifier::Ctrl),
+            (state.alt_key(), InputModifier::Alt),
+            (state.super_key(), InputModifier::Super),
+        ]
+        .into_iter()
+        .filter_map(|(pressed, modifier)| pressed.then_some(modifier))
+        .collect()
+    }
+
+    /// Run the action bound to the input, on the stacked message at `y` when there is one
+    pub(super) fn dispatch(&mut self, input: InputAction, y: Option<f32>) {
+        let Some(actions) = self.config.actions.as_ref() else {
+            return;
+        };
+        let Some(action) = binding(actions, &input, &self.pressed_modifiers()) else {
+            return;
+        };
+        if let Some(index) = y.and_then(|y| self.stacked_at(y)) {
+            if self.stacked_action(index, &action) {
+                return;
+            }
+        }
+        self.run_action(action);
+    }
+
+    pub(super) fn key_input(&mut self, event: &KeyEvent) {
+        if event.state != ElementState::Pressed || event.repeat || !self.show() {
+            return;
+        }
+        if let Some(name) = key_name(&event.logical_key) {
+            self.dispatch(InputAction::Key(name), None);
+        }
+    }
+
+    fn run_action(&mut self, action: NotificationAction) {
+        match action {
+            NotificationAction::Close => {
+                if let Some(id) = self.current_id {
+                    self.forget_kept(id, Some(CloseReason::Dismissed));
+                }
+                self.update(AppMessage::Close)
+            }
+            NotificationAction::OpenNotification => self.invoke_action("default"),
+            NotificationAction::InvokeAction(key) => self.invoke_action(&key),
+            NotificationAction::NextNotification => self.cycle_group(1),
+            NotificationAction::PreviousNotification => self.cycle_group(-1),
+            NotificationAction::CopyBody => self.copy_body(),
+            NotificationAction::Snooze(secs) => self.snooze(secs),
+            NotificationAction::DismissAll => self.dismiss_all(),
+            NotificationAction::ShowHistory => self.show_history(),
+        }
+    }
+
+    /// Invoke the action of the notification shown and close it, unless it is resident
+    fn invoke_action(&mut self, key: &str) {
+        if let (Some(broadcast), Some(id)) = (self.broadcast.clone(), self.current_id) {
+            let key = key.to_owned();
+            tokio::spawn(async move {
+                broadcast.notify_action::<Self>(id, &key).await;
+            });
+            if self.resident.contains(&id) {
+                return;
+            }
+            self.forget_kept(id, Some(CloseReason::Dismissed));
+            self.update(AppMessage::Close)
+        }
+    }
+
+    /// Notification shown, only the ones with an id are remembered
+    fn current_notification(&self) -> Option<&AppMessage> {
+        self.current_id
+            .and_then(|id| self.history.iter().find(|m| id_of(m) == Some(id)))
+    }
+
+    /// Keep the notification for the history, in its place when it is shown again
+    pub(super) fn remember(&mut self, msg: &AppMessage) {
+        let Some(id) = id_of(msg) else {
+            return;
+        };
+        if let Some(old) = self.history.iter_mut().find(|m| id_of(m) == Some(id)) {
+            *old = msg.clone();
+            return;
+        }
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(msg.clone());
+    }
+
+    fn copy_body(&mut self) {
+        if let Some(AppMessage::Notification { title, body, .. }) = self.current_notification() {
+            copy_to_clipboard(body.clone().unwrap_or_else(|| title.clone()));
+        }
+    }
+
+    fn snooze(&mut self, secs: f32) {
+        let Some(msg) = self.current_notification().cloned() else {
+            return;
+        };
+        // A negative, not finite or too long time would not be a point in time
+        let Some(until) = Duration::try_from_secs_f32(secs)
+            .ok()
+            .and_then(|d| Instant::now().checked_add(d))
+        else {
+            eprintln!("Invalid snooze time {secs}, ignoring the binding");
+            return;
+        };
+        // It is kept again when it comes back
+        if let Some(id) = id_of(&msg) {
+            self.forget_kept(id, None);
+        }
+        self.snoozed.push((until, msg));
+        self.update(AppMessage::Close);
+    }
+
+    /// Show again the snoozed notifications once their time is up
+    pub(super) fn wake_snoozed(&mut self, now: Instant) {
+        let Some(index) = self.snoozed.iter().position(|(until, _)| *until <= now) else {
+            return;
+        };
+        let (_, msg) = self.snoozed.remove(index);
+        self.update(msg);
+    }
+
+    fn dismiss_all(&mut self) {
+        let now = Instant::now();
+        self.group = None;
+        self.snoozed.clear();
+        for id in self.kept.iter().filter_map(id_of).collect::<Vec<_>>() {
+            self.forget_kept(id, Some(CloseReason::Dismissed));
+        }
+        for entry in &mut self.stack {
+            entry.close(now);
+        }
+        self.update(AppMessage::Close);
+    }
+
+    /// Show the last notifications as a group, from the most recent
+    fn show_history(&mut self) {
+        let mut group = NotificationGroup::new("History".to_owned());
+        for msg in &self.history {
+            group.push(msg.clone());
+        }
+        if let Some(msg) = group.selected() {
+            self.group = Some(group);
+            self.update(msg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::InputEvent;
+
+    use super::*;
+
+    #[test]
+    fn test_binding() {
+        let mut actions = Action::default();
+        actions.add(
+            InputAction::from("c".to_owned()),
+            InputEvent::new(NotificationAction::CopyBody)
+                .with_modifiers(InputModifier::Ctrl)
+                .with_modifiers(InputModifier::Shift),
+        );
+
+        let escape = InputAction::from("Escape".to_owned());
+        assert_eq!(escape, InputAction::Key("Escape".to_owned()));
+        assert_eq!(
+            InputAction::from("LeftClick".to_owned()),
+            InputAction::LeftClick
+        );
+
+        // Without modifiers it matches with any of them
+        assert_eq!(
+            binding(&actions, &escape, &[]),
+            Some(NotificationAction::Close)
+        );
+        assert_eq!(
+            binding(&actions, &escape, &[InputModifier::Alt]),
+            Some(NotificationAction::Close)
+        );
+
+        // Plain left click opens and with Shift dismisses all
+        assert_eq!(
+            binding(&actions, &InputAction::LeftClick, &[]),
+            Some(NotificationAction::OpenNotification)
+        );
+        assert_eq!(
+            binding(&actions, &InputAction::LeftClick, &[InputModifier::Shift]),
+            Some(NotificationAction::DismissAll)
+        );
+
+        let copy = InputAction::Key("c".to_owned());
+        assert_eq!(binding(&actions, &copy, &[]), None);
+        assert_eq!(binding(&actions, &copy, &[InputModifier::Ctrl]), None);
+        assert_eq!(
+            binding(
+                &actions,
+                &copy,
+                &[InputModifier::Shift, InputModifier::Ctrl]
+            ),
+            Some(NotificationAction::CopyBody)
+        );
+        assert_eq!(binding(&actions, &InputAction::TouchSwipeUp, &[]), None);
+    }
+}
@@ -1,38 +1,88 @@
 use std::time::Duration;
 
 use config::OsdType;
-use services::{Battery, Notification, ServiceBroadcast, ServiceReceive, SingletoneListener};
+use services::{
+    AudioDevice, AudioDeviceKind, Battery, BrightnessDevice, CaptureKind, CaptureState,
+    CloseReason, DiskUsage, HotplugAction, HotplugEvent, LockKey, MountEvent, NetworkEvent,
+    NetworkKind, Notification, NowPlaying, PlaybackStatus, PowerEvent, PowerProfile, Sensor,
+    ServiceBroadcast, ServiceReceive, SingletoneListener, Timeout,
+};
 
 use super::{App, AppMessage, MainApp, ICON_SIZE};
 
+/// Playback position, like `1:05` or `1:02:05`
+fn format_position(secs: f32) -> String {
+    let secs = secs.max(0.0) as u64;
+    if secs < 3600 {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    } else {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    }
+}
+
+/// Human readable battery estimation, like `≈ 18 min left`
+fn format_remaining(remaining: Duration) -> String {
+    let minutes = remaining.as_secs() / 60;
+    if minutes < 60 {
+        format!("≈ {minutes} min left")
+    } else {
+        format!("≈ {} h {:02} min left", minutes / 60, minutes % 60)
+    }
+}
+
+/// Human readable size, like `1.5 GiB`
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
 impl Notification for MainApp {
     fn notify(
         &mut self,
         id: u32,
+        app: Option<String>,
         title: String,
         icon: Option<services::Icon>,
         urgency: config::Urgency,
         body: Option<String>,
         value: Option<f32>,
         actions: Vec<String>,
-        timeout: Option<i32>,
+        timeout: Option<Timeout>,
+        resident: bool,
     ) -> zbus::fdo::Result<u32> {
         if actions.contains(&"close".into()) {
             return Ok(id);
         }
+        if resident {
+            self.resident.insert(id);
+        } else {
+            self.resident.remove(&id);
+        }
         if let Some(value) = value {
-            self.update(AppMessage::Slider {
+            // Sending again with `replaces_id` updates the shown slider in place
+            self.update(AppMessage::LiveSlider {
+                id,
                 icon,
-                value,
                 urgency,
                 timeout,
+                value: value * 100.0,
+                label: None,
                 bg: None,
                 fg: None,
-                id: Some(id),
                 output: None,
             })
         } else {
-            self.update(AppMessage::Notification {
+            let msg = AppMessage::Notification {
                 title,
                 body,
                 icon,
@@ -42,13 +92,25 @@ impl Notification for MainApp {
                 fg: None,
                 id: Some(id),
                 output: None,
-            })
+            };
+            match app {
+                Some(app) => self.show_grouped(app, msg),
+                None => self.update(msg),
+            }
         }
         Ok(id)
     }
 
-    fn close_notification(&mut self, _: u32) -> zbus::fdo::Result<()> {
-        self.update(AppMessage::Close);
+    fn close_notification(&mut self, id: u32) -> zbus::fdo::Result<()> {
+        // The notifications that never expire are only forgotten here, by the user or an action
+        self.forget_kept(id, Some(CloseReason::Closed));
+        if self.group.as_ref().is_some_and(|g| g.contains(Some(id))) {
+            self.close_grouped(id);
+        } else if self.current_id == Some(id) {
+            self.update(AppMessage::Close);
+        } else {
+            self.close_stacked(id);
+        }
         Ok(())
     }
 
@@ -58,21 +120,40 @@ impl Notification for MainApp {
 }
 
 impl ServiceReceive for MainApp {
-    fn batteries_below(&mut self, level: u8, _batteries: &[Battery]) {
+    fn batteries_below(&mut self, level: u8, batteries: &[Battery]) {
+        // Batteries came sorted by level, so the first is the most urgent
+        let remaining = batteries.first().and_then(Battery::time_to_empty);
         if let Some(battery_config) = self.config.battery.clone().level.as_ref() {
             for (alert_level, config) in &battery_config.0 {
                 if *alert_level >= level && !self.notified_levels.contains(alert_level) {
+                    let icon = (config.icon.clone(), self.get_icon_size()).try_into().ok();
+                    let timeout = config.show_duration.map(Timeout::Seconds);
                     // Send Notification
-                    self.update(AppMessage::Slider {
-                        id: None,
-                        output: None,
-                        urgency: config::Urgency::Normal,
-                        icon: (config.icon.clone(), self.get_icon_size()).try_into().ok(),
-                        timeout: config.show_duration.map(|d| d as i32),
-                        value: level as f32,
-                        bg: config.background.clone(),
-                        fg: config.foreground.clone(),
-                    });
+                    if let Some(remaining) = remaining {
+                        self.update(AppMessage::Notification {
+                            id: None,
+                            output: None,
+                            urgency: config::Urgency::Normal,
+                            title: format!("Battery {level}%"),
+                            body: Some(format_remaining(remaining)),
+                            icon,
+                            timeout,
+                            bg: config.background.clone(),
+                            fg: config.foreground.clone(),
+                        });
+                    } else {
+                        self.update(AppMessage::Slider {
+                            id: None,
+                            output: None,
+                            urgency: config::Urgency::Normal,
+                            icon,
+                            timeout,
+                            value: level as f32,
+                            label: None,
+                            bg: config.background.clone(),
+                            fg: config.foreground.clone(),
+                        });
+                    }
 
                     self.notified_levels.insert(*alert_level);
 
@@ -86,6 +167,387 @@ impl ServiceReceive for MainApp {
         }
     }
 
+    fn brightness_changed(&mut self, device: &BrightnessDevice) {
+        let brightness = &self.config.brightness;
+        let config = brightness.devices.get(device.name());
+        let icon = config
+            .map(|c| c.icon.clone())
+            .unwrap_or_else(|| brightness.icon.clone());
+
+        self.update(AppMessage::Slider {
+            id: None,
+            output: None,
+            urgency: config::Urgency::Low,
+            icon: (icon, self.get_icon_size()).try_into().ok(),
+            timeout: config.and_then(|c| c.show_duration).map(Timeout::Seconds),
+            value: device.percent() as f32,
+            label: None,
+            bg: config.and_then(|c| c.background.clone()),
+            fg: config.and_then(|c| c.foreground.clone()),
+        });
+    }
+
+    fn audio_changed(&mut self, device: &AudioDevice) {
+        let config = match device.kind() {
+            AudioDeviceKind::Output => self.config.audio.output.clone(),
+            AudioDeviceKind::Input => self.config.audio.input.clone(),
+        };
+        let (icon, bg, fg) = if device.muted() {
+            (
+                config.muted_icon,
+                config.muted_background,
+                config.muted_foreground,
+            )
+        } else {
+            (config.icon, config.background, config.foreground)
+        };
+
+        self.update(AppMessage::Slider {
+            id: None,
+            output: None,
+            urgency: config::Urgency::Low,
+            icon: (icon, self.get_icon_size()).try_into().ok(),
+            timeout: config.show_duration.map(Timeout::Seconds),
+            value: device.percent() as f32,
+            label: None,
+            bg,
+            fg,
+        });
+    }
+
+    fn lock_changed(&mut self, key: LockKey, active: bool) {
+        let keyboard = &self.config.keyboard;
+        let (name, config) = match key {
+            LockKey::CapsLock => ("Caps Lock", &keyboard.caps_lock),
+            LockKey::NumLock => ("Num Lock", &keyboard.num_lock),
+            LockKey::ScrollLock => ("Scroll Lock", &keyboard.scroll_lock),
+        };
+        if !config.enabled {
+            return;
+        }
+        let (icon, state) = if active {
+            (config.on_icon.clone(), "On")
+        } else {
+            (config.off_icon.clone(), "Off")
+        };
+
+        self.update(AppMessage::Notification {
+            id: None,
+            output: None,
+            title: format!("{name} {state}"),
+            body: None,
+            urgency: config::Urgency::Low,
+            icon: (icon, self.get_icon_size()).try_into().ok(),
+            timeout: keyboard.show_duration.map(Timeout::Seconds),
+            bg: None,
+            fg: None,
+        });
+    }
+
+    fn layout_changed(&mut self, layout: &str) {
+        let keyboard = &self.config.keyboard;
+        let title = keyboard
+            .layouts
+            .get(layout)
+            .cloned()
+            .unwrap_or_else(|| layout.to_owned());
+
+        self.update(AppMessage::Notification {
+            id: None,
+            output: None,
+            title,
+            body: None,
+            urgency: config::Urgency::Low,
+            icon: (keyboard.layout_icon.clone(), self.get_icon_size())
+                .try_into()
+                .ok(),
+            timeout: keyboard.show_duration.map(Timeout::Seconds),
+            bg: None,
+            fg: None,
+        });
+    }
+
+    fn now_playing(&mut self, now_playing: &NowPlaying) {
+        let config = &self.config.mpris;
+        let artist = now_playing.artist();
+        let body = match now_playing.status() {
+            PlaybackStatus::Playing => artist,
+            PlaybackStatus::Paused if artist.is_empty() => "Paused".to_owned(),
+            PlaybackStatus::Paused => format!("{artist} (Paused)"),
+            PlaybackStatus::Stopped => return,
+        };
+        let icon = now_playing
+            .art(self.get_icon_size())
+            .or_else(|| (config.icon.clone(), self.get_icon_size()).try_into().ok());
+
+        self.update(AppMessage::Notification {
+            // Clicking the OSD toggles the playback through this id
+            id: Some(now_playing.id()),
+            output: None,
+            title: now_playing.title().to_owned(),
+            body: (!body.is_empty()).then_some(body),
+            urgency: config::Urgency::Low,
+            icon,
+            timeout: config.show_duration.map(Timeout::Seconds),
+            bg: config.background.clone(),
+            fg: config.foreground.clone(),
+        });
+    }
+
+    fn network_changed(&mut self, event: &NetworkEvent) {
+        let network = &self.config.network;
+        let (config, connection) = match event {
+            NetworkEvent::Connected(connection) => match connection.kind() {
+                NetworkKind::Wifi => (&network.wifi_connected, connection),
+                NetworkKind::Ethernet => (&network.ethernet_connected, connection),
+                NetworkKind::Vpn => (&network.vpn_connected, connection),
+                NetworkKind::Other => return,
+            },
+            NetworkEvent::Disconnected(connection) => match connection.kind() {
+                NetworkKind::Wifi => (&network.wifi_disconnected, connection),
+                NetworkKind::Ethernet => (&network.ethernet_disconnected, connection),
+                NetworkKind::Vpn => (&network.vpn_disconnected, connection),
+                NetworkKind::Other => return,
+            },
+            NetworkEvent::Offline => {
+                let config = &network.offline;
+                if config.enabled {
+                    self.update(AppMessage::Notification {
+                        id: None,
+                        output: None,
+                        title: "Offline".to_owned(),
+                        body: None,
+                        urgency: config::Urgency::Normal,
+                        icon: (config.icon.clone(), self.get_icon_size()).try_into().ok(),
+                        timeout: config.show_duration.map(Timeout::Seconds),
+                        bg: config.background.clone(),
+                        fg: config.foreground.clone(),
+                    });
+                }
+                return;
+            }
+        };
+        if !config.enabled {
+            return;
+        }
+        let icon = (config.icon.clone(), self.get_icon_size()).try_into().ok();
+        let timeout = config.show_duration.map(Timeout::Seconds);
+
+        // The wifi strength is shown by the slider, labeled with the SSID
+        if let (NetworkEvent::Connected(_), Some(strength)) = (event, connection.strength()) {
+            self.update(AppMessage::Slider {
+                id: None,
+                output: None,
+                urgency: config::Urgency::Low,
+                icon,
+                timeout,
+                value: strength as f32,
+                label: Some(connection.name().to_owned()),
+                bg: config.background.clone(),
+                fg: config.foreground.clone(),
+            });
+            return;
+        }
+
+        self.update(AppMessage::Notification {
+            id: None,
+            output: None,
+            title: connection.name().to_owned(),
+            body: Some(match event {
+                NetworkEvent::Connected(_) => "Connected".to_owned(),
+                _ => "Disconnected".to_owned(),
+            }),
+            urgency: config::Urgency::Low,
+            icon,
+            timeout,
+            bg: config.background.clone(),
+            fg: config.foreground.clone(),
+        });
+    }
+
+    fn power_changed(&mut self, event: &PowerEvent) {
+        let config = &self.config.power;
+        let (icon, title) = match event {
+            PowerEvent::Profile(profile) => match profile {
+                PowerProfile::PowerSaver => (&config.power_saver_icon, "Power Saver"),
+                PowerProfile::Balanced => (&config.balanced_icon, "Balanced"),
+                PowerProfile::Performance => (&config.performance_icon, "Performance"),
+                PowerProfile::Other(name) => (&config.balanced_icon, name.as_str()),
+            },
+            PowerEvent::Lid { closed } if config.show_lid => (
+                &config.lid_icon,
+                if *closed { "Lid Closed" } else { "Lid Open" },
+            ),
+            _ => return,
+        };
+
+        self.update(AppMessage::Notification {
+            id: None,
+            output: None,
+            title: title.to_owned(),
+            body: None,
+            urgency: config::Urgency::Low,
+            icon: (icon.clone(), self.get_icon_size()).try_into().ok(),
+            timeout: config.show_duration.map(Timeout::Seconds),
+            bg: None,
+            fg: None,
+        });
+    }
+
+    fn temperature_alert(&mut self, level: u8, sensor: &Sensor) {
+        let Some(config) = self
+            .config
+            .hwmon
+            .level
+            .as_ref()
+            .and_then(|levels| levels.0.get(&level))
+            .cloned()
+        else {
+            return;
+        };
+        let body = match sensor.fan_rpm() {
+            Some(rpm) => format!("{} · Fan {rpm} RPM", sensor.chip()),
+            None => sensor.chip().to_owned(),
+        };
+
+        self.update(AppMessage::Notification {
+            id: None,
+            output: None,
+            urgency: config::Urgency::Normal,
+            title: format!("{} {:.0}°C", sensor.label(), sensor.temperature()),
+            body: Some(body),
+            icon: (config.icon, self.get_icon_size()).try_into().ok(),
+            timeout: config.show_duration.map(Timeout::Seconds),
+            bg: config.background,
+            fg: config.foreground,
+        });
+    }
+
+    fn disk_space_low(&mut self, level: u8, usage: &DiskUsage) {
+        let Some(config) = self
+            .config
+            .storage
+            .level
+            .as_ref()
+            .and_then(|levels| levels.0.get(&level))
+            .cloned()
+        else {
+            return;
+        };
+
+        self.update(AppMessage::Slider {
+            id: None,
+            output: None,
+            urgency: config::Urgency::Normal,
+            icon: (config.icon, self.get_icon_size()).try_into().ok(),
+            timeout: config.show_duration.map(Timeout::Seconds),
+            // The bar shows the used space, like the file managers
+            value: 100.0 - usage.percent_free() as f32,
+            label: Some(format!(
+                "{} {} free",
+                usage.mount_point().display(),
+                format_size(usage.available())
+            )),
+            bg: config.background,
+            fg: config.foreground,
+        });
+    }
+
+    fn mount_changed(&mut self, event: &MountEvent) {
+        let config = &self.config.storage;
+        if !config.show_mounts {
+            return;
+        }
+        let (icon, title, mount) = match event {
+            MountEvent::Mounted(mount) => (&config.mount_icon, "Mounted", mount),
+            MountEvent::Unmounted(mount) => (&config.unmount_icon, "Unmounted", mount),
+        };
+
+        self.update(AppMessage::Notification {
+            id: None,
+            output: None,
+            title: format!("{title} {}", mount.name()),
+            body: Some(mount.source().to_owned()),
+            urgency: config::Urgency::Low,
+            icon: (icon.clone(), self.get_icon_size()).try_into().ok(),
+            timeout: config.show_duration.map(Timeout::Seconds),
+            bg: None,
+            fg: None,
+        });
+    }
+
+    fn hotplug(&mut self, event: &HotplugEvent) {
+        let config = &self.config.hotplug;
+        let (icon, title) = match event.action {
+            HotplugAction::Added => (&config.added_icon, "Connected"),
+            HotplugAction::Removed => (&config.removed_icon, "Disconnected"),
+            HotplugAction::Changed => (&config.display_icon, "Changed"),
+        };
+
+        self.update(AppMessage::Notification {
+            id: None,
+            output: None,
+            title: format!("{} {title}", event.device.name()),
+            body: event.device.id().map(ToOwned::to_owned),
+            urgency: config::Urgency::Low,
+            icon: (icon.clone(), self.get_icon_size()).try_into().ok(),
+            timeout: config.show_duration.map(Timeout::Seconds),
+            bg: None,
+            fg: None,
+        });
+    }
+
+    fn privacy_changed(&mut self, state: &CaptureState) {
+        let config = &self.config.privacy;
+        let microphone = state.is_active(CaptureKind::Microphone);
+        let camera = state.is_active(CaptureKind::Camera);
+        let (icon, title) = match (microphone, camera) {
+            (true, true) => (&config.camera_icon, "Camera and Microphone in Use"),
+            (false, true) => (&config.camera_icon, "Camera in Use"),
+            (true, false) => (&config.microphone_icon, "Microphone in Use"),
+            (false, false) => {
+                self.update(AppMessage::Unpin { id: state.id() });
+                return;
+            }
+        };
+
+        self.update(AppMessage::Pin {
+            id: state.id(),
+            title: title.to_owned(),
+            urgency: config::Urgency::Critical,
+            icon: (icon.clone(), self.get_icon_size()).try_into().ok(),
+            body: Some(state.applications().join(", ")),
+            bg: config.background.clone(),
+            fg: config.foreground.clone(),
+            output: None,
+        });
+    }
+
+    fn resumed(&mut self, batteries: &[Battery]) {
+        let config = &self.config.power;
+        // Batteries came sorted by level, so the first is the most urgent
+        let Some(battery) = batteries.first().filter(|_| config.show_on_resume) else {
+            return;
+        };
+        // Forget the alerts of the levels it charged above while suspended
+        self.notified_levels
+            .retain(|level| battery.level() < *level);
+
+        self.update(AppMessage::Slider {
+            id: None,
+            output: None,
+            urgency: config::Urgency::Low,
+            icon: (config.resume_icon.clone(), self.get_icon_size())
+                .try_into()
+                .ok(),
+            timeout: config.show_duration.map(Timeout::Seconds),
+            value: battery.level() as f32,
+            label: battery.time_to_empty().map(format_remaining),
+            bg: None,
+            fg: None,
+        });
+    }
+
     fn set_broadcast(&mut self, broadcast: ServiceBroadcast) {
         self.broadcast.replace(broadcast);
     }
@@ -103,6 +565,8 @@ impl SingletoneListener<(Option<String>, Option<String>, Option<String>, OsdType
         let msg = match msg {
             OsdType::Daemon => None,
             OsdType::Init => None,
+            // Handled by the client, the daemon only sees the sysfs change
+            OsdType::Brightness { .. } => None,
             OsdType::Close => Some(AppMessage::Close),
             OsdType::Notification {
                 title,
@@ -120,6 +584,32 @@ impl SingletoneListener<(Option<String>, Option<String>, Option<String>, OsdType
                 id: None,
                 timeout: None,
             }),
+            OsdType::Progress {
+                id,
+                value,
+                image,
+                urgency,
+                elapsed,
+                total,
+            } => Some(AppMessage::LiveSlider {
+                id,
+                bg,
+                fg,
+                output: o,
+                value: value as f32,
+                urgency: urgency.unwrap_or_default(),
+                icon: image.and_then(|image| (image, self.get_icon_size()).try_into().ok()),
+                label: match (elapsed, total) {
+                    (Some(elapsed), Some(total)) => Some(format!(
+                        "{} / {}",
+                        format_position(elapsed),
+                        format_position(total)
+                    )),
+                    (Some(elapsed), None) => Some(format_position(elapsed)),
+                    _ => None,
+                },
+                timeout: None,
+            }),
             OsdType::Slider {
                 value,
                 image,
@@ -129,6 +619,7 @@ impl SingletoneListener<(Option<String>, Option<String>, Option<String>, OsdType
                 fg,
                 output: o,
                 value: value as f32,
+                label: None,
                 urgency: urgency.unwrap_or_default(),
                 icon: image.and_then(|image| (image, self.get_icon_size()).try_into().ok()),
                 id: None,
@@ -0,0 +1,451 @@
+use std::time::{Duration, Instant};
+
+use config::{NotificationAction, OsdAlign, OsdLayout};
+use cosmic_text::{Buffer, FontSystem, SwashCache};
+use raqote::{DrawTarget, Transform};
+use services::CloseReason;
+
+use crate::components::{Background, Badge, Component, IconComponent, Slider, Text};
+use crate::utils::ease_out_cubic;
+
+use super::event_loop::{ContentState, WindowState};
+use super::{AppMessage, MainApp};
+
+/// Notification kept on screen after the newest one with the stack layout
+pub(super) struct StackEntry {
+    id: Option<u32>,
+    background: Background,
+    icon: Option<IconComponent>,
+    slider: Option<Slider>,
+    title: Option<Text>,
+    description: Option<Text>,
+    badge: Option<Badge>,
+    icon_char: Buffer,
+    title_text: Buffer,
+    description_text: Buffer,
+    badge_text: Buffer,
+
+    shown_at: Instant,
+    show_duration: f32,
+    /// When it started to leave, by its timeout or because it was closed
+    exiting_at: Option<Instant>,
+    /// Slot of the stack it is moving to, the newest message is the slot `0`
+    slot: usize,
+    /// Where the last move started and when, so the stack reflows smoothly
+    from_slot: f32,
+    moved_at: Instant,
+}
+
+impl StackEntry {
+    fn current_slot(&self, now: Instant, animation_duration: f32) -> f32 {
+        let elapsed = now.duration_since(self.moved_at).as_secs_f32();
+        let progress = ease_out_cubic((elapsed / animation_duration).min(1.0));
+        self.from_slot + (self.slot as f32 - self.from_slot) * progress
+    }
+
+    fn move_to(&mut self, slot: usize, now: Instant, animation_duration: f32) {
+        if self.slot != slot {
+            self.from_slot = self.current_slot(now, animation_duration);
+            self.slot = slot;
+            self.moved_at = now;
+        }
+    }
+
+    pub(super) fn close(&mut self, now: Instant) {
+        self.exiting_at.get_or_insert(now);
+    }
+
+    /// Like the progress of the content, `1.0` while it is shown and `0.0` once it left
+    fn progress(&self, now: Instant, animation_duration: f32) -> f32 {
+        match self.exiting_at {
+            Some(exiting_at) => {
+                let elapsed = now.duration_since(exiting_at).as_secs_f32();
+                1.0 - ease_out_cubic((elapsed / animation_duration).min(1.0))
+            }
+            None => 1.0,
+        }
+    }
+
+    fn draw(
+        &mut self,
+        ctx: &mut DrawTarget,
+        progress: f32,
+        fonts: &mut FontSystem,
+        cache: &mut SwashCache,
+    ) {
+        self.background.draw(ctx, progress, ());
+        if let Some(slider) = self.slider.as_mut() {
+            slider.draw(ctx, progress, ());
+        }
+        if let Some(icon) = self.icon.as_mut() {
+            icon.draw(ctx, progress, (fonts, cache, &mut self.icon_char));
+        }
+        if let Some(title) = self.title.as_mut() {
+            title.draw(ctx, progress, (fonts, cache, &self.title_text));
+        }
+        if let Some(description) = self.description.as_mut() {
+            description.draw(ctx, progress, (fonts, cache, &self.description_text));
+        }
+        if let Some(badge) = self.badge.as_mut() {
+            badge.draw(ctx, progress, (fonts, cache, &self.badge_text));
+        }
+    }
+}
+
+impl MainApp {
+    /// Messages visible at once, only the stack layout shows more than one
+    fn stack_size(&self) -> usize {
+        let window = self.config.window.clone().unwrap_or_default();
+        match window.layout {
+            OsdLayout::Single => 1,
+            OsdLayout::Stack => window.stack_size.unwrap_or(3).max(1) as usize,
+        }
+    }
+
+    /// Height of each message, the surface is as high as the slots in use
+    fn slot_height(&self) -> f32 {
+        let window = self.config.window.clone().unwrap_or_default();
+        match window.position.is_side() {
+            true => window.width.unwrap_or(600) as f32,
+            false => window.height.unwrap_or(80) as f32,
+        }
+    }
+
+    fn main_visible(&self) -> bool {
+        !matches!(self.window_state, WindowState::Hidden)
+            || !matches!(self.content_state, ContentState::Idle)
+    }
+
+    /// Slots the surface needs, counting the ones still moving
+    pub(super) fn used_slots(&self) -> u32 {
+        let now = Instant::now();
+        let animation_duration = self.config.globals.animation_duration.unwrap_or(1.0);
+        self.stack
+            .iter()
+            .map(|e| e.current_slot(now, animation_duration).ceil() as u32 + 1)
+            .chain(self.main_visible().then_some(1))
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Whether the stack grows upward, like the surface aligned to the bottom of the screen
+    fn grows_upward(&self) -> bool {
+        self.config.window.clone().unwrap_or_default().align().1 == OsdAlign::End
+    }
+
+    /// Move the slot to its place, the stack grows downward from the top and upward from the
+    /// bottom
+    pub(super) fn slot_transform(&self, ctx: &DrawTarget, slot: f32) -> Transform {
+        let height = self.slot_height();
+        let y = match self.grows_upward() {
+            true => ctx.height() as f32 - (slot + 1.0) * height,
+            false => slot * height,
+        };
+        Transform::translation(0.0, y)
+    }
+
+    /// Whether the message makes room for itself moving the shown one down the stack
+    pub(super) fn should_stack(&self, msg: &AppMessage) -> bool {
+        let id = match msg {
+            AppMessage::Slider { id, .. } | AppMessage::Notification { id, .. } => *id,
+            _ => return false,
+        };
+        let same_group = self
+            .group
+            .as_ref()
+            .is_some_and(|g| g.contains(self.current_id) && g.contains(id));
+        self.stack_size() > 1
+            && !matches!(self.window_state, WindowState::Hidden)
+            && self.current_id != id
+            && !same_group
+            // The pinned message comes back by itself once it is replaced
+            && !(self.current_id.is_some() && self.current_id == self.pinned_id())
+    }
+
+    /// Move the shown message to the stack, leaving the components free for the next one
+    pub(super) fn push_to_stack(&mut self, now: Instant) {
+        let animation_duration = self.config.globals.animation_duration.unwrap_or(1.0);
+        let elapsed = match self.window_state {
+            WindowState::Showing { start_time } => now.duration_since(start_time).as_secs_f32(),
+            _ => 0.0,
+        };
+        let background = Background::new(&self.config, (None, None), ());
+        let title_metrics = self.title_text.metrics();
+        let description_metrics = self.description_text.metrics();
+        let badge_metrics = self.badge_text.metrics();
+        let icon_metrics = self.icon_char.metrics();
+
+        let entry = StackEntry {
+            id: self.current_id,
+            background: std::mem::replace(&mut self.background, background),
+            icon: self.icon.take(),
+            slider: self.slider.take(),
+            title: self.title.take(),
+            description: self.description.take(),
+            badge: self.badge.take(),
+            icon_char: std::mem::replace(
+                &mut self.icon_char,
+                Buffer::new(&mut self.fonts, icon_metrics),
+            ),
+            title_text: std::mem::replace(
+                &mut self.title_text,
+                Buffer::new(&mut self.fonts, title_metrics),
+            ),
+            description_text: std::mem::replace(
+                &mut self.description_text,
+                Buffer::new(&mut self.fonts, description_metrics),
+            ),
+            badge_text: std::mem::replace(
+                &mut self.badge_text,
+                Buffer::new(&mut self.fonts, badge_metrics),
+            ),
+            shown_at: now,
+            show_duration: (self.show_duration - elapsed).max(0.0),
+            exiting_at: None,
+            slot: 0,
+            from_slot: 0.0,
+            moved_at: now,
+        };
+        self.stack.insert(0, entry);
+        // The oldest ones leave at once when there is no room for them
+        self.stack.truncate(self.stack_size() - 1);
+
+        for (slot, entry) in self.stack.iter_mut().enumerate() {
+            entry.move_to(slot + 1, now, animation_duration);
+        }
+        self.window_state = WindowState::Hidden;
+        self.content_state = ContentState::Idle;
+    }
+
+    /// Draw the stacked messages, removing the ones that already left
+    pub(super) fn draw_stack(&mut self, ctx: &mut DrawTarget) {
+        let now = Instant::now();
+        let animation_duration = self.config.globals.animation_duration.unwrap_or(1.0);
+        let first_slot = usize::from(self.main_visible());
+
+        let hovered = self.hovered_at.is_some();
+        let mut stack = std::mem::take(&mut self.stack);
+        for entry in &mut stack {
+            if !hovered && entry.shown_at.elapsed().as_secs_f32() >= entry.show_duration {
+                entry.close(now);
+            }
+        }
+        stack.retain(|e| e.progress(now, animation_duration) > 0.0);
+
+        for (slot, entry) in stack.iter_mut().enumerate() {
+            entry.move_to(first_slot + slot, now, animation_duration);
+            let transform = self.slot_transform(ctx, entry.current_slot(now, animation_duration));
+            let progress = entry.progress(now, animation_duration);
+            ctx.set_transform(&transform);
+            entry.draw(ctx, progress, &mut self.fonts, &mut self.sw_cache);
+        }
+        ctx.set_transform(&Transform::identity());
+        self.stack = stack;
+    }
+
+    /// Push back the timers of the stacked messages, by the time they were paused
+    pub(super) fn delay_stack(&mut self, by: Duration) {
+        for entry in &mut self.stack {
+            entry.shown_at += by;
+        }
+    }
+
+    /// Index in the stack of the message drawn at `y`, `None` for the newest one
+    pub(super) fn stacked_at(&self, y: f32) -> Option<usize> {
+        if self.stack.is_empty() {
+            return None;
+        }
+        let height = self.slot_height();
+        let row = (y / height).floor().max(0.0) as usize;
+        let slot = match self.grows_upward() {
+            true => (self.used_slots() as usize).checked_sub(row + 1)?,
+            false => row,
+        };
+        slot.checked_sub(usize::from(self.main_visible()))
+            .filter(|index| *index < self.stack.len())
+    }
+
+    /// Run the action of the input on a stacked message, `false` when it isn't about one message
+    pub(super) fn stacked_action(&mut self, index: usize, action: &NotificationAction) -> bool {
+        let now = Instant::now();
+        let Some(entry) = self.stack.get_mut(index) else {
+            return false;
+        };
+        let id = entry.id;
+        let key = match action {
+            NotificationAction::Close => {
+                entry.close(now);
+                if let Some(id) = id {
+                    self.forget_kept(id, Some(CloseReason::Dismissed));
+                }
+                return true;
+            }
+            NotificationAction::OpenNotification => "default",
+            NotificationAction::InvokeAction(key) => key,
+            _ => return false,
+        };
+        if let (Some(broadcast), Some(id)) = (self.broadcast.clone(), id) {
+            let key = key.to_owned();
+            tokio::spawn(async move {
+                broadcast.notify_action::<Self>(id, &key).await;
+            });
+            if !self.resident.contains(&id) {
+                entry.close(now);
+                self.forget_kept(id, Some(CloseReason::Dismissed));
+            }
+        }
+        true
+    }
+
+    /// Whether the message with the id is still on screen in the stack
+    pub(super) fn is_stacked(&self, id: u32) -> bool {
+        self.stack
+            .iter()
+            .any(|e| e.id == Some(id) && e.exiting_at.is_none())
+    }
+
+    /// Close the stacked message with the id
+    pub(super) fn close_stacked(&mut self, id: u32) {
+        let now = Instant::now();
+        if let Some(entry) = self.stack.iter_mut().find(|e| e.id == Some(id)) {
+            entry.close(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Config, OsdPosition, Urgency, Window};
+    use services::Timeout;
+
+    use super::*;
+
+    /// Two messages stacked below the shown one, their moves already finished
+    fn stacked_app(position: OsdPosition, stack_size: u32) -> MainApp {
+        let mut config = Config::default();
+        config.globals.animation_duration = Some(1.0);
+        config.window = Some(Window {
+            position,
+            height: Some(80),
+            layout: OsdLayout::Stack,
+            stack_size: Some(stack_size),
+            ..Default::default()
+        });
+        let mut app = MainApp::from(config);
+
+        let now = Instant::now();
+        for id in 1..=3 {
+            app.current_id = Some(id);
+            app.window_state = WindowState::Showing { start_time: now };
+            app.push_to_stack(now);
+        }
+        app.current_id = Some(4);
+        app.window_state = WindowState::Showing { start_time: now };
+        finish_moves(&mut app);
+        app
+    }
+
+    fn finish_moves(app: &mut MainApp) {
+        for entry in &mut app.stack {
+            entry.moved_at -= Duration::from_secs(2);
+        }
+    }
+
+    #[test]
+    fn test_push_to_stack() {
+        let app = stacked_app(OsdPosition::Top, 3);
+        let ids: Vec<_> = app.stack.iter().map(|e| e.id).collect();
+        assert_eq!(ids, [Some(3), Some(2)]);
+        assert_eq!(app.used_slots(), 3);
+
+        // With one slot there is nothing to stack
+        let app = stacked_app(OsdPosition::Top, 1);
+        assert!(app.stack.is_empty());
+        assert_eq!(app.used_slots(), 1);
+    }
+
+    #[test]
+    fn test_used_slots_reflow() {
+        let mut app = stacked_app(OsdPosition::Top, 3);
+        let now = Instant::now();
+
+        // The last one keeps its room while it moves up to the free slot
+        app.stack.remove(0);
+        app.stack[0].move_to(1, now, 1.0);
+        assert_eq!(app.used_slots(), 3);
+        finish_moves(&mut app);
+        assert_eq!(app.used_slots(), 2);
+
+        app.window_state = WindowState::Hidden;
+        app.stack.clear();
+        assert_eq!(app.used_slots(), 1);
+    }
+
+    #[test]
+    fn test_stacked_at() {
+        // Growing downward the newest message is on the top
+        let mut app = stacked_app(OsdPosition::Top, 3);
+        assert_eq!(app.stacked_at(10.0), None);
+        assert_eq!(app.stacked_at(90.0), Some(0));
+        assert_eq!(app.stacked_at(170.0), Some(1));
+        assert_eq!(app.stacked_at(250.0), None);
+
+        app.window_state = WindowState::Hidden;
+        assert_eq!(app.stacked_at(90.0), Some(1));
+
+        // Growing upward the newest message is on the bottom
+        let mut app = stacked_app(OsdPosition::Bottom, 3);
+        assert_eq!(app.stacked_at(10.0), Some(1));
+        assert_eq!(app.stacked_at(90.0), Some(0));
+        assert_eq!(app.stacked_at(170.0), None);
+
+        app.stack.truncate(1);
+        assert_eq!(app.stacked_at(10.0), Some(0));
+        assert_eq!(app.stacked_at(90.0), None);
+    }
+
+    #[test]
+    fn test_kept_notifications() {
+        let mut app = stacked_app(OsdPosition::Top, 3);
+        let notification = |id, timeout| AppMessage::Notification {
+            id: Some(id),
+            title: format!("Notification {id}"),
+            urgency: Urgency::Normal,
+            icon: None,
+            timeout,
+            body: None,
+            bg: None,
+            fg: None,
+            output: None,
+        };
+        let next_kept_id = |app: &MainApp| match app.next_kept() {
+            Some(AppMessage::Notification { id, .. }) => id,
+            _ => None,
+        };
+
+        app.keep_unexpiring(&notification(7, Some(Timeout::Never)));
+        app.keep_unexpiring(&notification(8, Some(Timeout::Seconds(5.0))));
+        app.keep_unexpiring(&notification(2, Some(Timeout::Never)));
+        // The stacked one is still on screen, so the other comes back first
+        assert_eq!(next_kept_id(&app), Some(7));
+
+        // Replaced by a message that expires
+        app.keep_unexpiring(&AppMessage::LiveSlider {
+            id: 7,
+            urgency: Urgency::Normal,
+            icon: None,
+            timeout: None,
+            value: 50.0,
+            label: None,
+            bg: None,
+            fg: None,
+            output: None,
+        });
+        assert_eq!(next_kept_id(&app), None);
+
+        app.close_stacked(2);
+        assert_eq!(next_kept_id(&app), Some(2));
+        app.forget_kept(2, Some(CloseReason::Closed));
+        assert!(app.kept.is_empty());
+    }
+}
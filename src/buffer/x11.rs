@@ -5,6 +5,7 @@ use std::num::{NonZeroU16, NonZeroU32};
 use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
 use std::ptr::{null_mut, NonNull};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{io, slice};
 
 use as_raw_xcb_connection::AsRawXcbConnection;
@@ -15,14 +16,25 @@ use winit::raw_window_handle::{
 };
 use x11rb::connection::{Connection, SequenceNumber};
 use x11rb::cookie::Cookie;
-use x11rb::protocol::xproto::{ConnectionExt as _, ImageOrder, VisualClass, Visualid};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ClipOrdering, ConnectionExt as _, ImageOrder,
+    PropMode, VisualClass, Visualid,
+};
 use x11rb::protocol::{
+    shape::{self, ConnectionExt as _, SK, SO},
     shm::{self, ConnectionExt as _},
     xproto,
 };
+use x11rb::wrapper::ConnectionExt as _;
 use x11rb::xcb_ffi::XCBConnection;
 
 use super::{BufferInterface, Rect};
+use region::{visible_rects, ShapeRect};
+
+mod region;
+
+/// How often the compositing manager is looked up, it can start or stop at any time
+const COMPOSITOR_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct X11DisplayImpl<H: ?Sized> {
     connection: Arc<XCBConnection>,
@@ -47,6 +59,24 @@ pub struct X11DisplayImpl<H: ?Sized> {
     /// The current buffer width/height.
     size: Option<(NonZeroU16, NonZeroU16)>,
 
+    /// The SHAPE extension is available to let the input through the transparent pixels.
+    has_shape: bool,
+
+    /// The input shape set last, `None` until the first frame is presented.
+    input_shape: Option<Vec<ShapeRect>>,
+
+    /// The `_NET_WM_CM_S<n>` selection, owned by the compositing manager of the screen.
+    compositor_atom: Atom,
+
+    /// A compositing manager blends the transparent pixels with the windows below.
+    composited: bool,
+
+    /// When the compositing manager was looked up last.
+    compositor_checked: Instant,
+
+    /// The bounding shape set last while there is no compositing manager.
+    bounding_shape: Option<Vec<ShapeRect>>,
+
     /// Keep the window alive.
     _window_handle: H,
 }
@@ -219,6 +249,32 @@ impl<H: HasWindowHandle + HasDisplayHandle> BufferInterface<H> for X11DisplayImp
             Buffer::Wire(Vec::new())
         };
 
+        let has_shape = matches!(
+            connection.extension_information(shape::X11_EXTENSION_NAME),
+            Ok(Some(_))
+        );
+        if !has_shape {
+            println!("SHAPE extension is not available. Clicks will not pass through the OSD.");
+        }
+
+        if let Err(e) = set_osd_hints(&connection, window) {
+            println!("Failed to set the OSD window hints: {e}");
+        }
+
+        let compositor_atom = connection
+            .intern_atom(
+                false,
+                format!("_NET_WM_CM_S{}", xcb_handle.screen).as_bytes(),
+            )
+            .map_err(|_| "Failed to send intern atom request")?
+            .reply()
+            .map_err(|_| "Failed to get intern atom reply")?
+            .atom;
+        let composited = has_compositor(&connection, compositor_atom);
+        if !composited {
+            println!("No compositing manager is running. The OSD will be drawn opaque.");
+        }
+
         Ok(Self {
             connection: connection.clone(),
             window,
@@ -228,6 +284,12 @@ impl<H: HasWindowHandle + HasDisplayHandle> BufferInterface<H> for X11DisplayImp
             buffer,
             buffer_presented: false,
             size: None,
+            has_shape,
+            input_shape: None,
+            compositor_atom,
+            composited,
+            compositor_checked: Instant::now(),
+            bounding_shape: None,
             _window_handle: handle,
         })
     }
@@ -267,6 +329,14 @@ impl<H: HasWindowHandle + HasDisplayHandle> BufferInterface<H> for X11DisplayImp
             .size
             .expect("Must set size of surface before calling `present_with_damage()`");
 
+        self.check_compositor();
+        if !self.composited {
+            self.make_opaque(surface_width.get())?;
+        }
+        if self.has_shape {
+            self.update_input_shape(surface_width.get())?;
+        }
+
         match self.buffer {
             Buffer::Wire(ref wire) => {
                 // This is a suboptimal strategy, raise a stink in the debug logs.
@@ -358,6 +428,90 @@ impl<H: HasWindowHandle + HasDisplayHandle> BufferInterface<H> for X11DisplayImp
     }
 }
 
+impl<H: ?Sized> X11DisplayImpl<H> {
+    fn set_shape(&self, kind: SK, rects: &[ShapeRect]) -> Result<(), Box<dyn Error>> {
+        let rectangles: Vec<xproto::Rectangle> = rects.iter().map(|&r| r.into()).collect();
+        self.connection
+            .shape_rectangles(
+                SO::SET,
+                kind,
+                ClipOrdering::UNSORTED,
+                self.window,
+                0,
+                0,
+                &rectangles,
+            )
+            .map(|c| c.ignore_error())
+            .map_err(|_| "Failed to set the window shape")?;
+
+        Ok(())
+    }
+
+    /// Let the input through the transparent pixels of the frame, all of them while hidden.
+    fn update_input_shape(&mut self, width: u16) -> Result<(), Box<dyn Error>> {
+        self.buffer.finish_wait(&self.connection)?;
+        // SAFETY: We just waited for the last `shm::PutImage` request.
+        let rects = visible_rects(unsafe { self.buffer.buffer_mut() }, width);
+        if self.input_shape.as_ref() != Some(&rects) {
+            self.set_shape(SK::INPUT, &rects)?;
+            self.input_shape = Some(rects);
+        }
+
+        Ok(())
+    }
+
+    /// Follow the compositing manager starting or stopping, like picom on a plain window manager.
+    fn check_compositor(&mut self) {
+        if self.compositor_checked.elapsed() < COMPOSITOR_INTERVAL {
+            return;
+        }
+        self.compositor_checked = Instant::now();
+
+        let composited = has_compositor(&self.connection, self.compositor_atom);
+        if composited == self.composited {
+            return;
+        }
+        self.composited = composited;
+        self.bounding_shape = None;
+        if composited {
+            println!("A compositing manager started. The OSD will be drawn transparent.");
+            if self.has_shape {
+                // Back to the rectangle of the window, the transparent pixels are blended now
+                _ = self
+                    .connection
+                    .shape_mask(SO::SET, SK::BOUNDING, self.window, 0, 0, x11rb::NONE)
+                    .map(|c| c.ignore_error());
+            }
+        } else {
+            println!("The compositing manager stopped. The OSD will be drawn opaque.");
+        }
+    }
+
+    /// Without a compositing manager the alpha of the pixels is ignored, so the transparent ones
+    /// would be black and the translucent ones darker.
+    ///
+    /// The pixels are drawn with their full color, and the window is cut to the background drawn
+    /// in the frame, all of it while hidden.
+    fn make_opaque(&mut self, width: u16) -> Result<(), Box<dyn Error>> {
+        self.buffer.finish_wait(&self.connection)?;
+        // SAFETY: We just waited for the last `shm::PutImage` request.
+        let pixels = unsafe { self.buffer.buffer_mut() };
+        pixels.iter_mut().for_each(|p| *p = unpremultiply(*p));
+        if !self.has_shape {
+            return Ok(());
+        }
+
+        // Every pixel drawn is kept, a translucent background is shown opaque as a whole
+        let rects = visible_rects(pixels, width);
+        if self.bounding_shape.as_ref() != Some(&rects) {
+            self.set_shape(SK::BOUNDING, &rects)?;
+            self.bounding_shape = Some(rects);
+        }
+
+        Ok(())
+    }
+}
+
 impl ShmBuffer {
     /// Allocate a new `ShmSegment` of the given size.
     fn alloc_segment(
@@ -540,6 +694,72 @@ fn create_shm_id() -> io::Result<OwnedFd> {
     ))
 }
 
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_WM_WINDOW_TYPE,
+        _NET_WM_WINDOW_TYPE_NOTIFICATION,
+        _NET_WM_STATE,
+        _NET_WM_STATE_ABOVE,
+        _NET_WM_STATE_SKIP_TASKBAR,
+        _NET_WM_STATE_SKIP_PAGER,
+    }
+}
+
+/// Keep the window away from the window manager, the taskbars and the pagers.
+///
+/// The window manager only looks at them when the window is mapped, so it must not be yet.
+fn set_osd_hints(c: &impl Connection, window: xproto::Window) -> Result<(), Box<dyn Error>> {
+    let atoms = Atoms::new(c)?.reply()?;
+
+    c.change_window_attributes(
+        window,
+        &ChangeWindowAttributesAux::new().override_redirect(1),
+    )?
+    .ignore_error();
+    c.change_property32(
+        PropMode::REPLACE,
+        window,
+        atoms._NET_WM_WINDOW_TYPE,
+        AtomEnum::ATOM,
+        &[atoms._NET_WM_WINDOW_TYPE_NOTIFICATION],
+    )?
+    .ignore_error();
+    c.change_property32(
+        PropMode::REPLACE,
+        window,
+        atoms._NET_WM_STATE,
+        AtomEnum::ATOM,
+        &[
+            atoms._NET_WM_STATE_ABOVE,
+            atoms._NET_WM_STATE_SKIP_TASKBAR,
+            atoms._NET_WM_STATE_SKIP_PAGER,
+        ],
+    )?
+    .ignore_error();
+
+    Ok(())
+}
+
+/// Whether a compositing manager owns the `_NET_WM_CM_S<n>` selection of the screen.
+fn has_compositor(c: &impl Connection, selection: Atom) -> bool {
+    c.get_selection_owner(selection)
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .is_some_and(|reply| reply.owner != x11rb::NONE)
+}
+
+/// Get the color of a premultiplied pixel without its alpha, keeping the alpha.
+#[inline]
+fn unpremultiply(pixel: u32) -> u32 {
+    let alpha = pixel >> 24;
+    if alpha == 0 || alpha == 0xff {
+        return pixel;
+    }
+    let channel = |shift: u32| ((((pixel >> shift) & 0xff) * 0xff / alpha).min(0xff)) << shift;
+
+    (alpha << 24) | channel(16) | channel(8) | channel(0)
+}
+
 /// Test to see if SHM is available.
 fn is_shm_available(c: &impl Connection) -> bool {
     // Create a small SHM segment.
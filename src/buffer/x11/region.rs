@@ -0,0 +1,127 @@
+use std::iter::once;
+
+use x11rb::protocol::xproto::Rectangle;
+
+/// Rectangle of a window shape, in window coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShapeRect {
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl From<ShapeRect> for Rectangle {
+    fn from(rect: ShapeRect) -> Self {
+        Rectangle {
+            x: rect.x,
+            y: rect.y,
+            width: rect.width,
+            height: rect.height,
+        }
+    }
+}
+
+/// Runs of pixels not fully transparent in a row, as their start and length
+fn spans(row: &[u32]) -> Vec<(i16, u16)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    // The trailing transparent pixel closes the run reaching the end of the row
+    for (x, pixel) in row.iter().chain(once(&0)).enumerate() {
+        match (pixel >> 24 > 0, start) {
+            (true, None) => start = Some(x),
+            (false, Some(s)) => {
+                spans.push((s as i16, (x - s) as u16));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+/// Rectangles covering the pixels not fully transparent, empty for a blank frame
+///
+/// The rows with the same runs are merged, so the shape of the rounded background takes a
+/// rectangle for each row of the corners and one for the rest
+pub fn visible_rects(pixels: &[u32], width: u16) -> Vec<ShapeRect> {
+    let mut rects = Vec::new();
+    // Rectangles of the last row, they grow down while the next rows have the same runs
+    let mut open: Vec<ShapeRect> = Vec::new();
+
+    for (y, row) in pixels.chunks_exact(width as usize).enumerate() {
+        let spans = spans(row);
+        let same = open.len() == spans.len()
+            && open
+                .iter()
+                .zip(&spans)
+                .all(|(rect, &(x, width))| rect.x == x && rect.width == width);
+        if same {
+            open.iter_mut().for_each(|rect| rect.height += 1);
+            continue;
+        }
+        rects.append(&mut open);
+        open = spans
+            .into_iter()
+            .map(|(x, width)| ShapeRect {
+                x,
+                y: y as i16,
+                width,
+                height: 1,
+            })
+            .collect();
+    }
+    rects.append(&mut open);
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_rects() {
+        const O: u32 = 0xff202020;
+        const T: u32 = 0x00000000;
+        // Half transparent pixels of the antialiased edges are visible too
+        const H: u32 = 0x80101010;
+
+        assert_eq!(visible_rects(&[T; 12], 4), []);
+
+        #[rustfmt::skip]
+        let pixels = [
+            T, H, O, O, H, T,
+            O, O, O, O, O, O,
+            O, O, O, O, O, O,
+            O, O, T, T, O, O,
+        ];
+        let rect = |x, y, width, height| ShapeRect {
+            x,
+            y,
+            width,
+            height,
+        };
+        assert_eq!(
+            visible_rects(&pixels, 6),
+            [
+                rect(1, 0, 4, 1),
+                rect(0, 1, 6, 2),
+                rect(0, 3, 2, 1),
+                rect(4, 3, 2, 1),
+            ]
+        );
+
+        // A translucent background is kept whole, not only the text over it
+        const B: u32 = 0x20080808;
+        #[rustfmt::skip]
+        let pixels = [
+            T, B, B, B, B, T,
+            B, B, O, O, B, B,
+            T, B, B, B, B, T,
+        ];
+        assert_eq!(
+            visible_rects(&pixels, 6),
+            [rect(1, 0, 4, 1), rect(0, 1, 6, 1), rect(1, 2, 4, 1)]
+        );
+    }
+}
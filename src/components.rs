@@ -3,11 +3,13 @@ use raqote::DrawTarget;
 use config::Config;
 
 mod background;
+mod badge;
 mod icon;
 mod slider;
 mod text;
 
 pub use background::Background;
+pub use badge::Badge;
 pub use icon::IconComponent;
 pub use slider::Slider;
 pub use text::Text;
@@ -53,7 +53,7 @@ impl Component<'_> for Background {
     fn draw(&mut self, ctx: &mut raqote::DrawTarget, progress: f32, _: Self::DrawArgs) {
         let or = self.radius; // Origin radius
         let rp = or * progress; // Radius progress
-        let (start_height, animated_height) = if self.position == OsdPosition::Bottom {
+        let (start_height, animated_height) = if self.position.is_bottom() {
             (self.height, self.height * (1.0 - progress))
         } else {
             (0.0, self.height * progress)
@@ -0,0 +1,114 @@
+use std::f32::consts::PI;
+
+use config::OsdPosition;
+use cosmic_text::{Buffer, Color, FontSystem, SwashCache};
+use raqote::{DrawOptions, DrawTarget, PathBuilder, SolidSource, Source};
+
+use super::Component;
+
+/// Pill with a short text, like the count of a notification group
+pub struct Badge {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    padding: f32,
+    bg: SolidSource,
+    fg: SolidSource,
+    position: OsdPosition,
+}
+
+impl Badge {
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Move the badge so it ends at `x`
+    pub fn end_at(&mut self, x: f32) {
+        self.x = x - self.width;
+    }
+}
+
+impl<'a> Component<'a> for Badge {
+    /// Font size, text width, fill color and text color
+    type Args = (f32, f32, SolidSource, SolidSource);
+    type DrawArgs = (&'a mut FontSystem, &'a mut SwashCache, &'a Buffer);
+
+    fn new(
+        config: &config::Config,
+        (x, y): (Option<f32>, Option<f32>),
+        (font_size, text_width, bg, fg): Self::Args,
+    ) -> Self {
+        let position = config.window.clone().unwrap_or_default().position;
+        let padding = font_size * 0.5;
+        let height = font_size * 1.4;
+
+        Self {
+            x: x.unwrap_or_default(),
+            y: y.map(|y| y - height / 2.0).unwrap_or_default(),
+            width: text_width + padding * 2.0,
+            height,
+            padding,
+            bg,
+            fg,
+            position,
+        }
+    }
+
+    fn draw(
+        &mut self,
+        ctx: &mut DrawTarget,
+        progress: f32,
+        (fonts, cache, buffer): Self::DrawArgs,
+    ) {
+        let y = if self.position.is_bottom() {
+            self.y + (self.y * (1.0 - progress))
+        } else {
+            self.y * progress
+        };
+        let alpha = progress.powf(2.3);
+        let r = self.height / 2.0;
+
+        let mut pb = PathBuilder::new();
+        pb.move_to(self.x + r, y);
+        pb.line_to(self.x + self.width - r, y);
+        pb.arc(self.x + self.width - r, y + r, r, -PI / 2.0, PI);
+        pb.line_to(self.x + r, y + self.height);
+        pb.arc(self.x + r, y + r, r, PI / 2.0, PI);
+        pb.close();
+        ctx.fill(
+            &pb.finish(),
+            &Source::Solid(SolidSource::from_unpremultiplied_argb(
+                (self.bg.a as f32 * alpha) as u8,
+                self.bg.r,
+                self.bg.g,
+                self.bg.b,
+            )),
+            &DrawOptions::default(),
+        );
+
+        let text_alpha = self.fg.a as f32 * alpha;
+        let text_y = y + (self.height - buffer.metrics().line_height) / 2.0;
+        buffer.draw(
+            fonts,
+            cache,
+            Color::rgba(self.fg.r, self.fg.g, self.fg.b, text_alpha as u8),
+            |px, py, w, h, color| {
+                let source = Source::Solid(SolidSource::from_unpremultiplied_argb(
+                    ((color.a() as f32 / 255.0) * text_alpha) as u8,
+                    color.r(),
+                    color.g(),
+                    color.b(),
+                ));
+                ctx.fill_rect(
+                    self.x + self.padding + px as f32,
+                    text_y + py as f32,
+                    w as f32,
+                    h as f32,
+                    &source,
+                    &DrawOptions::default(),
+                );
+            },
+        );
+    }
+}
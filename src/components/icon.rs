@@ -40,7 +40,7 @@ impl<'a> Component<'a> for IconComponent {
         (fonts, cache, buffer): Self::DrawArgs,
     ) {
         let alpha = (self.c.a as f32 * (progress.powf(2.3))).min(255.0);
-        let y = if self.position == OsdPosition::Bottom {
+        let y = if self.position.is_bottom() {
             self.y + (self.y * (1.0 - progress))
         } else {
             self.y * progress
@@ -1,8 +1,10 @@
+use std::time::Instant;
+
 use raqote::{DrawOptions, Path, PathBuilder, SolidSource, Source};
 
 use config::OsdPosition;
 
-use crate::utils::{adjust_brightness, contrast_ratio, ToColor};
+use crate::utils::{adjust_brightness, contrast_ratio, ease_out_cubic, ToColor};
 
 use super::Component;
 
@@ -10,7 +12,12 @@ pub struct Slider {
     x: f32,
     y: f32,
     size: f32,
+    // Room kept at the end for a label
+    reserved: f32,
     value: f32,
+    // Value animated from, and when the animation started
+    from: f32,
+    changed_at: Option<Instant>,
     rounded: f32,
     c: SolidSource,
     bg: SolidSource,
@@ -38,9 +45,39 @@ fn generate_colors(fg: (u8, u8, u8), bg_x: (u8, u8, u8)) -> ((u8, u8, u8), (u8,
     (slider_bg, slider_fg)
 }
 
+/// Duration of the transition between two values (in seconds)
+const VALUE_ANIMATION: f32 = 0.3;
+
 impl Slider {
     pub fn change_value(&mut self, value: f32) {
         self.value = value.clamp(0.036, 1.0);
+        self.changed_at = None;
+    }
+
+    /// Move smoothly to the new value, from 0 to 100 like `Slider::new`
+    pub fn animate_value(&mut self, value: f32) {
+        self.from = self.current_value();
+        self.value = (value / 100.0).clamp(0.036, 1.0);
+        self.changed_at = Some(Instant::now());
+    }
+
+    /// The value drawn now, in the middle of the animation
+    pub fn current_value(&self) -> f32 {
+        let Some(changed_at) = self.changed_at else {
+            return self.value;
+        };
+        let progress = (changed_at.elapsed().as_secs_f32() / VALUE_ANIMATION).min(1.0);
+        self.from + (self.value - self.from) * ease_out_cubic(progress)
+    }
+
+    /// Keep room at the end of the slider, like for the elapsed time label
+    pub fn reserve_end(&mut self, room: f32) {
+        self.reserved = room;
+    }
+
+    /// Where the full slider ends, including the reserved room
+    pub fn end(&self) -> f32 {
+        self.x + self.size
     }
 
     pub fn change_size(&mut self, value: f32) {
@@ -138,7 +175,10 @@ impl Component<'_> for Slider {
             c,
             bg,
             size,
+            reserved: 0.0,
             value,
+            from: value,
+            changed_at: None,
             rounded,
             position,
             x: x.unwrap_or(radius * 2.4),
@@ -148,15 +188,16 @@ impl Component<'_> for Slider {
     }
 
     fn draw(&mut self, ctx: &mut raqote::DrawTarget, progress: f32, _: Self::DrawArgs) {
-        let y = if self.position == OsdPosition::Bottom {
+        let y = if self.position.is_bottom() {
             self.y + (self.y * (1.0 - progress))
         } else {
             self.y * progress
         };
-        let slider_width = self.size * self.value;
+        let size = self.size - self.reserved;
+        let slider_width = size * self.current_value();
 
         // Fondo del slider
-        let bg = self.draw_slide(y, self.size);
+        let bg = self.draw_slide(y, size);
         ctx.fill(
             &bg,
             &Source::Solid(raqote::SolidSource::from_unpremultiplied_argb(
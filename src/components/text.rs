@@ -18,6 +18,34 @@ pub struct Text {
     is_overflow: bool,
     text_width: f32,
     last_update: Instant,
+    /// The marquee stays where it is, like while the pointer is over it
+    paused: bool,
+}
+
+impl Text {
+    /// Keep room at the end of the text, like for a badge
+    pub fn reserve_end(&mut self, room: f32) {
+        self.max_width -= room;
+        self.is_overflow = self.text_width >= (self.max_width + 2.0);
+    }
+
+    /// Where the text ends when it is as wide as it can be
+    pub fn end(&self) -> f32 {
+        self.x + self.max_width
+    }
+
+    pub fn pause(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Move the marquee by `delta`, `false` when the text fits and there is nothing to scroll
+    pub fn scrub(&mut self, delta: f32) -> bool {
+        if !self.is_overflow {
+            return false;
+        }
+        self.scroll_x = (self.scroll_x + delta).clamp(0.0, self.text_width - self.max_width);
+        true
+    }
 }
 
 impl<'a> Component<'a> for Text {
@@ -45,6 +73,7 @@ impl<'a> Component<'a> for Text {
             x: x.unwrap_or((radius * 2.0) - 10.0),
             y: y.map(|y| y - (font_size / 2.0)).unwrap_or(0.0),
             last_update: Instant::now(),
+            paused: false,
         }
     }
 
@@ -57,13 +86,15 @@ impl<'a> Component<'a> for Text {
         let font_size = buffer.metrics().font_size;
 
         let mut pb = PathBuilder::new();
-        let y = if self.position == OsdPosition::Bottom {
+        let y = if self.position.is_bottom() {
             self.y + (self.y * (1.0 - progress))
         } else {
             self.y * progress
         };
 
-        if self.is_overflow {
+        if self.is_overflow && self.paused {
+            self.last_update = Instant::now();
+        } else if self.is_overflow {
             let scroll_speed = 30.0;
             let max_x_offset = self.text_width - self.max_width;
             let now = Instant::now();
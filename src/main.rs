@@ -1,15 +1,17 @@
 use parking_lot::Mutex;
+use std::path::PathBuf;
 use std::sync::{Arc, OnceLock};
 
 mod app;
 mod buffer;
 mod components;
+mod output;
 mod utils;
 mod window;
 
 use app::MainApp;
-use config::{get_config, write_default, Config, OsdType, Parser, ProjectDirs};
-use services::ServiceManager;
+use config::{get_config, write_default, BrightnessCommand, Config, OsdType, Parser, ProjectDirs};
+use services::{set_brightness, ServiceManager};
 use window::Window;
 
 static PROJECT_PATH: OnceLock<ProjectDirs> = OnceLock::new();
@@ -28,6 +30,25 @@ async fn main() {
         return;
     }
 
+    if let OsdType::Brightness {
+        device,
+        subsystem,
+        action,
+    } = &config.command
+    {
+        let result = set_brightness(*subsystem, device.as_deref(), |current| match action {
+            BrightnessCommand::Set { value } => *value,
+            BrightnessCommand::Inc { value } => current.saturating_add(*value),
+            BrightnessCommand::Dec { value } => current.saturating_sub(*value),
+        })
+        .await;
+        if let Err(e) = result {
+            eprintln!("Cannot change the brightness: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let app = Arc::new(Mutex::new(MainApp::from(config.clone())));
 
     {
@@ -49,6 +70,64 @@ async fn main() {
             )
             .await
             .unwrap()
+            .with_brightness(
+                config.brightness.enabled,
+                &config
+                    .brightness
+                    .devices
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap()
+            .with_audio(config.audio.enabled)
+            .with_hwmon(
+                config.hwmon.enabled,
+                config.hwmon.refresh_time,
+                config.hwmon.sensors.clone(),
+                config
+                    .hwmon
+                    .level
+                    .as_ref()
+                    .map(|l| l.0.keys().copied().collect::<Vec<_>>())
+                    .unwrap_or_default(),
+                config.hwmon.hysteresis,
+            )
+            .with_storage(
+                config.storage.enabled,
+                config.storage.refresh_time,
+                config
+                    .storage
+                    .mount_points
+                    .iter()
+                    .map(PathBuf::from)
+                    .collect(),
+                config
+                    .storage
+                    .level
+                    .as_ref()
+                    .map(|l| l.0.keys().copied().collect::<Vec<_>>())
+                    .unwrap_or_default(),
+                config.storage.show_mounts,
+            )
+            .unwrap()
+            .with_hotplug(
+                config.hotplug.enabled,
+                config.hotplug.subsystems.clone(),
+                config.hotplug.allow.clone(),
+                config.hotplug.deny.clone(),
+            )
+            .with_privacy(config.privacy.enabled)
+            .with_keyboard(config.keyboard.enabled, config.keyboard.refresh_time)
+            .unwrap()
+            .with_mpris(config.mpris.enabled)
+            .await
+            .unwrap()
+            .with_network(config.network.enabled)
+            .await
+            .unwrap()
+            .with_power(config.power.enabled, config.power.show_lid)
+            .await
             .with_singletone()
             .await
             .unwrap();
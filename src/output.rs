@@ -0,0 +1,30 @@
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::xproto::ConnectionExt as _;
+
+use crate::buffer::is_wayland;
+
+/// Name of the RandR monitor under the pointer, the X server has no keyboard focus per monitor
+fn pointer_output() -> Option<String> {
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots.get(screen)?.root;
+    let pointer = conn.query_pointer(root).ok()?.reply().ok()?;
+    let (x, y) = (pointer.root_x as i32, pointer.root_y as i32);
+
+    let monitors = conn.randr_get_monitors(root, true).ok()?.reply().ok()?;
+    let monitor = monitors.monitors.into_iter().find(|m| {
+        let (mx, my) = (m.x as i32, m.y as i32);
+        (mx..mx + m.width as i32).contains(&x) && (my..my + m.height as i32).contains(&y)
+    })?;
+    let name = conn.get_atom_name(monitor.name).ok()?.reply().ok()?;
+    String::from_utf8(name.name).ok()
+}
+
+/// Name of the output with the focus, from the compositor on Wayland and the pointer on X11
+pub fn focused_output() -> Option<String> {
+    if is_wayland() {
+        services::focused_output()
+    } else {
+        pointer_output()
+    }
+}
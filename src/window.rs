@@ -1,4 +1,9 @@
-use std::{collections::HashMap, num::NonZero, sync::Arc};
+use std::{
+    collections::HashMap,
+    num::NonZero,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use parking_lot::Mutex;
 use raqote::DrawTarget;
@@ -7,17 +12,27 @@ use winit::{
     application::ApplicationHandler,
     dpi::{LogicalPosition, LogicalSize},
     event::WindowEvent,
-    event_loop::{ActiveEventLoop, EventLoop},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
     monitor::MonitorHandle,
-    platform::wayland::{Anchor, Layer, WindowAttributesWayland},
+    platform::wayland::{Anchor, KeyboardInteractivity, Layer, WindowAttributesWayland},
     window::{WindowAttributes, WindowId},
 };
 
 use crate::{
     app::App,
     buffer::{is_wayland, new_buffer, Buffer, BufferInterface},
+    output::focused_output,
 };
-use config::{Config, OsdPosition};
+use config::{Config, OsdAlign, OsdKeyboard, OsdLayer, FOCUSED_OUTPUT};
+use outputs::{watch_outputs, OutputEvent, OutputInfo, Outputs};
+use placement::{place, Margins};
+
+mod outputs;
+mod placement;
+
+/// The event loop can learn about a hotplug after the watcher, so the outputs are read once
+/// more after this
+const OUTPUTS_SETTLE: Duration = Duration::from_millis(500);
 
 pub(crate) trait AppTy: App + Sized + Send + Sync {}
 
@@ -25,92 +40,136 @@ impl<T: App + Sized + Send + Sync> AppTy for T {}
 
 pub struct Window<T: AppTy> {
     width: u32,
+    /// Height of each message, the surfaces are as high as the slots in use
     height: u32,
-    position: OsdPosition,
+    align: (OsdAlign, OsdAlign),
+    margins: Margins,
+    layer: Layer,
+    /// Keyboard focus while the OSD is shown
+    keyboard: KeyboardInteractivity,
+    slots: u32,
 
     context: DrawTarget,
     render: Arc<Mutex<T>>,
     windows: HashMap<WindowId, WindowState>,
+    outputs: Outputs,
+    /// When the outputs are read again after a hotplug
+    recheck_outputs: Option<Instant>,
+    /// Output with the focus when the OSD appeared, `Some(None)` when it is unknown
+    focused: Option<Option<String>>,
 
     // Inputs
     // region: WlRegion,
     active_input: bool,
+    /// Output drawing the OSD while the input is taken, `None` when every output draws it
+    input_output: Option<String>,
     safe_left: i32,
     max_width: i32,
-    safe_area: Option<Region>,
-    passthrought_area: Option<Region>,
+}
+
+fn output_name(screen: &MonitorHandle) -> String {
+    screen
+        .name()
+        .map(|s| s.to_string())
+        .unwrap_or(screen.native_id().to_string())
 }
 
 fn create_window<T: AppTy>(
     app: &Window<T>,
-    output: String,
+    output: OutputInfo,
     event_loop: &dyn ActiveEventLoop,
     window_attrs: WindowAttributes,
     screen: MonitorHandle,
 ) -> Option<WindowState> {
-    let LogicalSize::<u32> {
-        width: sw,
-        height: sh,
-    } = screen
-        .current_video_mode()
-        .unwrap()
-        .size()
-        .to_logical(screen.scale_factor());
-    let w = app.width;
-    let h = app.height;
-    let (x, y) = match app.position {
-        OsdPosition::Top => ((sw / 2) - w / 2, 0),
-        OsdPosition::Left => (0, (sh / 2) - h / 2),
-        OsdPosition::Right => (sw - w, (sh / 2) - h / 2),
-        OsdPosition::Bottom => ((sw / 2) - w / 2, sh - h),
-    };
+    let (sw, sh) = output.size;
+    let (w, h) = (app.width, app.height);
+    let (x, y) = place(output.size, (w, h), app.align, app.margins);
     println!(
         "Screen({:?}): ({sw}, {sh}) => {:?} ({x}, {y}, {w}, {h})",
         screen.name(),
-        app.position
+        app.align
     );
 
+    // The compositor centers the surface in the axis without anchors, and keeps it out of the
+    // exclusive zones of the panels because its own exclusive zone is left at 0
+    let anchor = match app.align.0 {
+        OsdAlign::Start => Anchor::LEFT,
+        OsdAlign::Center => Anchor::empty(),
+        OsdAlign::End => Anchor::RIGHT,
+    } | match app.align.1 {
+        OsdAlign::Start => Anchor::TOP,
+        OsdAlign::Center => Anchor::empty(),
+        // The stack grows upward from the bottom, so the surface stays at the bottom edge
+        OsdAlign::End => Anchor::BOTTOM,
+    };
+    let Margins {
+        top,
+        right,
+        bottom,
+        left,
+    } = app.margins;
     let window_attrs = if is_wayland() {
         window_attrs.with_platform_attributes(Box::new(
             WindowAttributesWayland::default()
                 .with_layer_shell()
-                .with_anchor(Anchor::LEFT | Anchor::TOP | Anchor::RIGHT)
-                .with_layer(Layer::Overlay)
-                .with_margin(y as i32, x as i32, 0, x as i32)
+                .with_anchor(anchor)
+                .with_layer(app.layer)
+                .with_keyboard_interactivity(KeyboardInteractivity::None)
+                .with_margin(top as i32, right as i32, bottom as i32, left as i32)
                 .with_region(LogicalPosition::new(0, 0), LogicalSize::new(0, 0))
                 .with_output(screen.native_id()),
         ))
     } else {
-        window_attrs.with_position(LogicalPosition::new(x, y))
+        // Mapped once the buffer made it override-redirect
+        window_attrs
+            .with_position(app.x11_position(LogicalPosition::new(x, y)))
+            .with_visible(false)
     };
 
     Some(WindowState::new(
         app,
         output,
-        event_loop.create_window(window_attrs).unwrap(),
+        LogicalPosition::new(x, y),
+        event_loop.create_window(window_attrs).ok()?,
     ))
 }
 
 impl<T: AppTy> Window<T> {
     pub fn run(render: Arc<Mutex<T>>, config: Config) {
-        let Config { window, .. } = &config;
+        let Config { window, output, .. } = &config;
+        let window = window.clone().unwrap_or_default();
+        let align = window.align();
+        let margins = Margins::from(&window);
+        let layer = match window.layer {
+            OsdLayer::Background => Layer::Background,
+            OsdLayer::Bottom => Layer::Bottom,
+            OsdLayer::Top => Layer::Top,
+            OsdLayer::Overlay => Layer::Overlay,
+        };
+        let keyboard = match window.keyboard {
+            OsdKeyboard::None => KeyboardInteractivity::None,
+            OsdKeyboard::OnDemand => KeyboardInteractivity::OnDemand,
+            OsdKeyboard::Exclusive => KeyboardInteractivity::Exclusive,
+        };
         let config::Window {
             position,
             width,
             height,
             radius,
             ..
-        } = window.clone().unwrap_or_default();
+        } = window;
         let width = width.unwrap_or(600);
         let height = height.unwrap_or(80);
         let radius = radius.unwrap_or(100) as f32;
         let safe_left = (radius * 2.0) - 20.0;
         let max_width = width as f32 - (radius * 3.7);
-        let (width, height) = match position {
-            OsdPosition::Bottom | OsdPosition::Top => (width, height),
-            OsdPosition::Left | OsdPosition::Right => (height, width),
+        let (width, height) = match position.is_side() {
+            false => (width, height),
+            true => (height, width),
         };
         let event_loop = EventLoop::new().unwrap();
+        let proxy = event_loop.create_proxy();
+        watch_outputs(move || proxy.wake_up());
         let windows = HashMap::with_capacity(4);
 
         let context = DrawTarget::new(width as i32, height as i32);
@@ -121,10 +180,16 @@ impl<T: AppTy> Window<T> {
             render,
             context,
             windows,
-            position,
+            outputs: Outputs::new(output.clone()),
+            recheck_outputs: None,
+            focused: None,
+            align,
+            margins,
+            layer,
+            keyboard,
+            slots: 1,
             active_input: false,
-            safe_area: None,
-            passthrought_area: None,
+            input_output: None,
             max_width: max_width as i32,
             safe_left: safe_left as i32,
         };
@@ -133,9 +198,11 @@ impl<T: AppTy> Window<T> {
     }
 
     pub fn draw(&mut self, event: &WindowEvent) -> (Option<String>, bool) {
-        let mut render = self.render.lock();
+        let render = self.render.clone();
+        let mut render = render.lock();
         render.event(event);
         let show = render.show();
+        self.resize_slots(render.slots());
 
         // Draw to the window:
         self.context.clear(raqote::SolidSource {
@@ -151,56 +218,147 @@ impl<T: AppTy> Window<T> {
 
         (render.get_output(), show)
     }
-}
 
-impl<T: AppTy> ApplicationHandler for Window<T> {
-    fn can_create_surfaces(&mut self, event_loop: &dyn ActiveEventLoop) {
+    /// Follow the focus when the output is `focused`, looking it up once each time the OSD appears
+    ///
+    /// Without a window in the focused output, like when it was just plugged, every output shows it
+    fn resolve_output(&mut self, output: Option<String>, show: bool) -> Option<String> {
+        if output.as_deref() != Some(FOCUSED_OUTPUT) {
+            return output;
+        }
+        if !show {
+            self.focused = None;
+            return None;
+        }
+        self.focused
+            .get_or_insert_with(|| {
+                focused_output().filter(|o| self.windows.values().any(|w| w.output == *o))
+            })
+            .clone()
+    }
+
+    /// Whether the window of `output` takes the input, only the ones drawing the OSD do
+    fn takes_input(&self, output: &str) -> bool {
+        self.active_input && self.input_output.as_ref().map_or(true, |o| o == output)
+    }
+
+    /// Where the window goes on X11 with the slots in use
+    ///
+    /// X11 has no anchors, the window moves up to keep its bottom edge in place
+    fn x11_position(&self, origin: LogicalPosition<u32>) -> LogicalPosition<u32> {
+        if self.align.1 != OsdAlign::End {
+            return origin;
+        }
+        let y = (origin.y + self.height).saturating_sub(self.height * self.slots);
+        LogicalPosition::new(origin.x, y)
+    }
+
+    /// Grow or shrink the surfaces to fit the messages shown at once
+    fn resize_slots(&mut self, slots: u32) {
+        if slots == self.slots {
+            return;
+        }
+        self.slots = slots;
+        let height = self.height * slots;
+        self.context = DrawTarget::new(self.width as i32, height as i32);
+        let mut windows = std::mem::take(&mut self.windows);
+        for window_state in windows.values_mut() {
+            window_state.resize(self.width, height);
+            // The input region covers every slot
+            window_state.update_safe_area(self.safe_left, self.max_width, height);
+            window_state.set_input(self.takes_input(&window_state.output));
+            if !is_wayland() {
+                let position = self.x11_position(window_state.origin);
+                window_state.window.set_outer_position(position.into());
+            }
+        }
+        self.windows = windows;
+    }
+
+    /// Create the windows of the plugged outputs and drop the ones of the unplugged outputs
+    fn sync_outputs(&mut self, event_loop: &dyn ActiveEventLoop) {
+        let mut screens = HashMap::new();
+        let current = event_loop
+            .available_monitors()
+            .filter_map(|screen| {
+                let name = output_name(&screen);
+                let size: LogicalSize<u32> = screen
+                    .current_video_mode()?
+                    .size()
+                    .to_logical(screen.scale_factor());
+                screens.insert(name.clone(), screen);
+                Some(OutputInfo {
+                    name,
+                    size: (size.width, size.height),
+                })
+            })
+            .collect::<Vec<_>>();
+        let events = self.outputs.update(current);
+        if events.is_empty() {
+            return;
+        }
+
         let window_attributes = WindowAttributes::default()
             .with_decorations(false)
             .with_transparent(true)
-            .with_surface_size(LogicalSize::new(self.width, self.height))
+            .with_surface_size(LogicalSize::new(self.width, self.height * self.slots))
             .with_window_level(winit::window::WindowLevel::AlwaysOnTop);
 
-        let output = {
-            let lock = self.render.lock();
-            lock.get_output()
-        };
-
-        for screen in event_loop.available_monitors() {
-            let name = screen
-                .name()
-                .map(|s| s.to_string())
-                .unwrap_or(screen.native_id().to_string());
-            if output.as_ref().is_some_and(|o| *o != name) {
-                continue;
-            }
-            let window_attributes = window_attributes
-                .clone()
-                .with_title(format!("__sosd_{name}"));
-            let Some(window_state) =
-                create_window(self, name, event_loop, window_attributes, screen)
-            else {
-                continue;
-            };
-            let window_id = window_state.window.id();
-            if self.safe_area.is_none() && is_wayland() {
-                let window = window_state
-                    .window
-                    .cast_ref::<winit::platform::wayland::Window>()
-                    .unwrap();
-
-                let safe_area = window.create_region().unwrap();
-                safe_area.add(self.safe_left, 0, self.max_width, self.height as i32);
-                self.safe_area.replace(safe_area);
-
-                let passthrought_area = window.create_region().unwrap();
-                passthrought_area.add(0, 0, 0, 0);
-                self.passthrought_area.replace(passthrought_area);
+        for event in events {
+            match event {
+                OutputEvent::Removed(name) => {
+                    self.windows.retain(|window_id, window_state| {
+                        let keep = window_state.output != name;
+                        if !keep {
+                            println!("Removed window with id={window_id:?} of {name}");
+                        }
+                        keep
+                    });
+                    // Look for the focus again, it could be in the unplugged output
+                    self.focused = None;
+                }
+                OutputEvent::Added(info) => {
+                    let Some(screen) = screens.remove(&info.name) else {
+                        continue;
+                    };
+                    let window_attributes = window_attributes
+                        .clone()
+                        .with_title(format!("__sosd_{}", info.name));
+                    let Some(window_state) =
+                        create_window(self, info, event_loop, window_attributes, screen)
+                    else {
+                        continue;
+                    };
+                    let window_id = window_state.window.id();
+                    println!("Created new window with id={window_id:?}");
+                    self.windows.insert(window_id, window_state);
+                }
             }
-            println!("Created new window with id={window_id:?}");
-            self.windows.insert(window_id, window_state);
         }
     }
+}
+
+impl<T: AppTy> ApplicationHandler for Window<T> {
+    fn can_create_surfaces(&mut self, event_loop: &dyn ActiveEventLoop) {
+        self.sync_outputs(event_loop);
+    }
+
+    /// Woken up by the outputs watcher
+    fn proxy_wake_up(&mut self, event_loop: &dyn ActiveEventLoop) {
+        self.sync_outputs(event_loop);
+        self.recheck_outputs = Some(Instant::now() + OUTPUTS_SETTLE);
+    }
+
+    fn about_to_wait(&mut self, event_loop: &dyn ActiveEventLoop) {
+        if self.recheck_outputs.is_some_and(|at| at <= Instant::now()) {
+            self.recheck_outputs = None;
+            self.sync_outputs(event_loop);
+        }
+        event_loop.set_control_flow(match self.recheck_outputs {
+            Some(at) => ControlFlow::WaitUntil(at),
+            None => ControlFlow::Wait,
+        });
+    }
 
     fn window_event(
         &mut self,
@@ -212,6 +370,7 @@ impl<T: AppTy> ApplicationHandler for Window<T> {
             return;
         }
         let (window_draw, can_show) = self.draw(&event);
+        let window_draw = self.resolve_output(window_draw, can_show);
         let window = self.windows.get_mut(&window_id).unwrap();
 
         if window_draw.as_ref().is_some_and(|o| *o != window.output) {
@@ -232,26 +391,20 @@ impl<T: AppTy> ApplicationHandler for Window<T> {
         #[allow(clippy::collapsible_if)]
         #[allow(clippy::collapsible_else_if)]
         if can_show {
-            if !self.active_input && is_wayland() {
+            // The message can move to another output while it is shown
+            if (!self.active_input || self.input_output != window_draw) && is_wayland() {
                 self.active_input = true;
-                for window_state in self.windows.values_mut() {
-                    let window = window_state
-                        .window
-                        .cast_ref::<winit::platform::wayland::Window>()
-                        .unwrap();
-                    window.set_region(self.safe_area.as_ref());
+                self.input_output = window_draw;
+                for window_state in self.windows.values() {
+                    window_state.set_input(self.takes_input(&window_state.output));
                 }
                 println!("Active input");
             }
         } else {
             if self.active_input && is_wayland() {
                 self.active_input = false;
-                for window_state in self.windows.values_mut() {
-                    let window = window_state
-                        .window
-                        .cast_ref::<winit::platform::wayland::Window>()
-                        .unwrap();
-                    window.set_region(self.passthrought_area.as_ref());
+                for window_state in self.windows.values() {
+                    window_state.set_input(false);
                 }
                 println!("Disable input");
             }
@@ -262,21 +415,32 @@ impl<T: AppTy> ApplicationHandler for Window<T> {
 /// State of the window.
 struct WindowState {
     output: String,
+    /// Logical size of the output, the input region can't go past it
+    size: (u32, u32),
+    /// Where the window was placed for a single message
+    origin: LogicalPosition<u32>,
     /// Render surface.
     ///
     /// NOTE: This surface must be dropped before the `Window`.
     pub buffer: Buffer<Arc<dyn winit::window::Window>>,
     /// The actual winit Window.
     pub window: Arc<dyn winit::window::Window>,
+
+    // Inputs, only on Wayland, on X11 the buffer shapes the input to the visible pixels
+    safe_area: Option<Region>,
+    passthrought_area: Option<Region>,
+    keyboard: KeyboardInteractivity,
 }
 
 impl WindowState {
     fn new<T: AppTy>(
         app: &Window<T>,
-        output: String,
+        output: OutputInfo,
+        origin: LogicalPosition<u32>,
         window: Box<dyn winit::window::Window>,
     ) -> Self {
         let window: Arc<dyn winit::window::Window> = Arc::from(window);
+        let height = app.height * app.slots;
 
         // SAFETY: the surface is dropped before the `window` which provided it with handle, thus
         // it doesn't outlive it.
@@ -284,15 +448,83 @@ impl WindowState {
         buffer
             .resize(
                 NonZero::new(app.width).unwrap(),
-                NonZero::new(app.height).unwrap(),
+                NonZero::new(height).unwrap(),
             )
             .unwrap();
+        if !is_wayland() {
+            window.set_visible(true);
+        }
 
-        Self {
-            output,
+        let passthrought_area = is_wayland().then(|| {
+            let window = window
+                .cast_ref::<winit::platform::wayland::Window>()
+                .unwrap();
+            let passthrought_area = window.create_region().unwrap();
+            passthrought_area.add(0, 0, 0, 0);
+            passthrought_area
+        });
+
+        let mut window_state = Self {
+            output: output.name,
+            size: output.size,
+            origin,
             buffer,
             window,
+            safe_area: None,
+            passthrought_area,
+            keyboard: app.keyboard,
+        };
+        window_state.update_safe_area(app.safe_left, app.max_width, height);
+        window_state.set_input(app.takes_input(&window_state.output));
+        window_state
+    }
+
+    /// Build the region taking the input for a surface `height` high, clipped to the output
+    fn update_safe_area(&mut self, left: i32, width: i32, height: u32) {
+        if !is_wayland() {
+            return;
         }
+        let window = self
+            .window
+            .cast_ref::<winit::platform::wayland::Window>()
+            .unwrap();
+        let (sw, sh) = self.size;
+        let left = left.min(sw as i32);
+        let safe_area = window.create_region().unwrap();
+        safe_area.add(left, 0, width.min(sw as i32 - left), height.min(sh) as i32);
+        self.safe_area.replace(safe_area);
+    }
+
+    /// Take the input in the safe area while the OSD is shown, otherwise it goes to the windows
+    /// below
+    ///
+    /// The keyboard is only taken while it is shown, an exclusive focus would lock it otherwise
+    fn set_input(&self, active: bool) {
+        if !is_wayland() {
+            return;
+        }
+        let window = self
+            .window
+            .cast_ref::<winit::platform::wayland::Window>()
+            .unwrap();
+        let region = match active {
+            true => &self.safe_area,
+            false => &self.passthrought_area,
+        };
+        window.set_region(region.as_ref());
+        window.set_keyboard_interactivity(match active {
+            true => self.keyboard,
+            false => KeyboardInteractivity::None,
+        });
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        _ = self
+            .window
+            .request_surface_size(LogicalSize::new(width, height).into());
+        self.buffer
+            .resize(NonZero::new(width).unwrap(), NonZero::new(height).unwrap())
+            .unwrap();
     }
 
     fn draw(&mut self, buff: &[u32]) {
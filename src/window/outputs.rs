@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+
+use config::FOCUSED_OUTPUT;
+use smithay_client_toolkit::output::{OutputHandler, OutputState};
+use smithay_client_toolkit::reexports::client::globals::registry_queue_init;
+use smithay_client_toolkit::reexports::client::protocol::wl_output::WlOutput;
+use smithay_client_toolkit::reexports::client::{Connection, QueueHandle};
+use smithay_client_toolkit::registry::{ProvidesRegistryState, RegistryState};
+use smithay_client_toolkit::{delegate_output, delegate_registry, registry_handlers};
+use x11rb::connection::Connection as _;
+use x11rb::protocol::randr::{ConnectionExt as _, NotifyMask};
+
+use crate::buffer::is_wayland;
+
+/// Monitor seen by the event loop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputInfo {
+    pub name: String,
+    /// Logical size, the window is placed from it
+    pub size: (u32, u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputEvent {
+    Added(OutputInfo),
+    Removed(String),
+}
+
+/// Outputs with a window, compared with the monitors of the event loop to follow the hotplug
+#[derive(Debug)]
+pub struct Outputs {
+    /// Output of the configuration, the others have no window
+    wanted: Option<String>,
+    known: BTreeMap<String, (u32, u32)>,
+}
+
+impl Outputs {
+    /// Follow the `output` of the configuration, every output has a window when it follows the
+    /// focus
+    pub fn new(output: Option<String>) -> Self {
+        Self {
+            wanted: output.filter(|o| o != FOCUSED_OUTPUT),
+            known: BTreeMap::new(),
+        }
+    }
+
+    /// Changes since the last update, an output with a new size is removed and added again
+    /// because its window must be placed again
+    pub fn update(&mut self, current: impl IntoIterator<Item = OutputInfo>) -> Vec<OutputEvent> {
+        let current = current
+            .into_iter()
+            .filter(|o| self.wanted.as_ref().map_or(true, |w| *w == o.name))
+            .map(|o| (o.name, o.size))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut events = Vec::new();
+        for (name, size) in &self.known {
+            if current.get(name) != Some(size) {
+                events.push(OutputEvent::Removed(name.clone()));
+            }
+        }
+        for (name, size) in &current {
+            if self.known.get(name) != Some(size) {
+                events.push(OutputEvent::Added(OutputInfo {
+                    name: name.clone(),
+                    size: *size,
+                }));
+            }
+        }
+        self.known = current;
+        events
+    }
+}
+
+/// Listens the `wl_output` globals in its own connection, winit does not tell about them
+struct WaylandOutputs {
+    registry_state: RegistryState,
+    output_state: OutputState,
+    on_change: Box<dyn Fn() + Send>,
+}
+
+impl OutputHandler for WaylandOutputs {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: WlOutput) {
+        (self.on_change)();
+    }
+
+    fn update_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: WlOutput) {
+        (self.on_change)();
+    }
+
+    fn output_destroyed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: WlOutput) {
+        (self.on_change)();
+    }
+}
+
+delegate_output!(WaylandOutputs);
+delegate_registry!(WaylandOutputs);
+
+impl ProvidesRegistryState for WaylandOutputs {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    registry_handlers!(OutputState);
+}
+
+fn watch_wayland(on_change: Box<dyn Fn() + Send>) -> Option<()> {
+    let conn = Connection::connect_to_env().ok()?;
+    let (globals, mut event_queue) = registry_queue_init(&conn).ok()?;
+    let qh = event_queue.handle();
+    let mut outputs = WaylandOutputs {
+        registry_state: RegistryState::new(&globals),
+        output_state: OutputState::new(&globals, &qh),
+        on_change,
+    };
+    loop {
+        event_queue.blocking_dispatch(&mut outputs).ok()?;
+    }
+}
+
+fn watch_x11(on_change: Box<dyn Fn() + Send>) -> Option<()> {
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots.get(screen)?.root;
+    let mask = NotifyMask::SCREEN_CHANGE | NotifyMask::CRTC_CHANGE | NotifyMask::OUTPUT_CHANGE;
+    conn.randr_select_input(root, mask).ok()?;
+    conn.flush().ok()?;
+    // Only the RandR events were selected
+    while conn.wait_for_event().is_ok() {
+        on_change();
+    }
+    None
+}
+
+/// Call `on_change` from another thread each time an output is plugged, unplugged or changes its
+/// mode, the compositor announces the `wl_output` globals and X11 the RandR changes
+pub fn watch_outputs(on_change: impl Fn() + Send + 'static) {
+    let on_change = Box::new(on_change);
+    std::thread::spawn(move || {
+        let watched = match is_wayland() {
+            true => watch_wayland(on_change),
+            false => watch_x11(on_change),
+        };
+        if watched.is_none() {
+            eprintln!("Cannot follow the outputs, the hotplugged ones get no window");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(name: &str, width: u32, height: u32) -> OutputInfo {
+        OutputInfo {
+            name: name.to_owned(),
+            size: (width, height),
+        }
+    }
+
+    #[test]
+    fn test_outputs_hotplug() {
+        let mut outputs = Outputs::new(Some(FOCUSED_OUTPUT.to_owned()));
+        let laptop = output("eDP-1", 1920, 1200);
+        let projector = output("HDMI-A-1", 1024, 768);
+
+        assert_eq!(
+            outputs.update([laptop.clone()]),
+            [OutputEvent::Added(laptop.clone())]
+        );
+        assert!(outputs.update([laptop.clone()]).is_empty());
+
+        // Plugging the projector
+        assert_eq!(
+            outputs.update([laptop.clone(), projector.clone()]),
+            [OutputEvent::Added(projector.clone())]
+        );
+
+        // The projector changes its mode
+        let projector_hd = output("HDMI-A-1", 1920, 1080);
+        assert_eq!(
+            outputs.update([projector_hd.clone(), laptop.clone()]),
+            [
+                OutputEvent::Removed("HDMI-A-1".to_owned()),
+                OutputEvent::Added(projector_hd)
+            ]
+        );
+
+        // Unplugging it, and closing the lid when docked
+        assert_eq!(
+            outputs.update([laptop]),
+            [OutputEvent::Removed("HDMI-A-1".to_owned())]
+        );
+        assert_eq!(
+            outputs.update([]),
+            [OutputEvent::Removed("eDP-1".to_owned())]
+        );
+        assert_eq!(
+            outputs.update([projector.clone()]),
+            [OutputEvent::Added(projector)]
+        );
+    }
+
+    #[test]
+    fn test_configured_output() {
+        let mut outputs = Outputs::new(Some("HDMI-A-1".to_owned()));
+        let laptop = output("eDP-1", 1920, 1200);
+        let projector = output("HDMI-A-1", 1024, 768);
+
+        // Nothing to show until the configured output is plugged
+        assert!(outputs.update([laptop.clone()]).is_empty());
+        assert_eq!(
+            outputs.update([laptop.clone(), projector.clone()]),
+            [OutputEvent::Added(projector)]
+        );
+        assert_eq!(
+            outputs.update([laptop]),
+            [OutputEvent::Removed("HDMI-A-1".to_owned())]
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use config::OsdAlign;
+
+/// Space left between the OSD and the edges of the screen
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Margins {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32,
+}
+
+impl From<&config::Window> for Margins {
+    fn from(window: &config::Window) -> Self {
+        Self {
+            top: window.margin_top.unwrap_or_default(),
+            right: window.margin_right.unwrap_or_default(),
+            bottom: window.margin_bottom.unwrap_or_default(),
+            left: window.margin_left.unwrap_or_default(),
+        }
+    }
+}
+
+/// Offset along one axis, centered between the margins
+fn offset(align: OsdAlign, screen: u32, size: u32, start: u32, end: u32) -> u32 {
+    match align {
+        OsdAlign::Start => start,
+        OsdAlign::Center => start + screen.saturating_sub(start + end + size) / 2,
+        OsdAlign::End => screen.saturating_sub(size + end),
+    }
+}
+
+/// Top left corner of the OSD in a screen of `screen` size
+pub fn place(
+    screen: (u32, u32),
+    size: (u32, u32),
+    (halign, valign): (OsdAlign, OsdAlign),
+    margins: Margins,
+) -> (u32, u32) {
+    (
+        offset(halign, screen.0, size.0, margins.left, margins.right),
+        offset(valign, screen.1, size.1, margins.top, margins.bottom),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use config::OsdPosition;
+
+    use super::*;
+
+    #[test]
+    fn test_place() {
+        let screen = (1920, 1080);
+        let size = (600, 80);
+        let none = Margins::default();
+
+        assert_eq!(
+            place(screen, size, OsdPosition::Top.align(), none),
+            (660, 0)
+        );
+        assert_eq!(
+            place(screen, size, OsdPosition::Bottom.align(), none),
+            (660, 1000)
+        );
+        assert_eq!(
+            place(screen, (80, 600), OsdPosition::Right.align(), none),
+            (1840, 240)
+        );
+        assert_eq!(
+            place(screen, size, OsdPosition::BottomLeft.align(), none),
+            (0, 1000)
+        );
+
+        // Below a status bar of 30 pixels, away from the right edge
+        let margins = Margins {
+            top: 30,
+            right: 20,
+            ..Default::default()
+        };
+        assert_eq!(
+            place(screen, size, OsdPosition::TopRight.align(), margins),
+            (1300, 30)
+        );
+        assert_eq!(
+            place(screen, (80, 600), OsdPosition::Left.align(), margins),
+            (0, 255)
+        );
+
+        // Bigger than the screen
+        assert_eq!(
+            place((500, 60), size, OsdPosition::BottomRight.align(), none),
+            (0, 0)
+        );
+    }
+}